@@ -1,6 +1,9 @@
-use std::{env, error::Error, fs::File, io::BufReader, process, str::FromStr, sync::Arc};
+use std::{collections::HashMap, env, error::Error, fs, io::Read, path::Path, process, sync::Arc};
 
-use boon::{Compiler, Draft, Schemas, UrlLoader};
+use boon::{
+    Catalog, CompileError, Compiler, Draft, ParseError, SchemaIndex, Schemas, SourceFormat,
+    SourceMap, UrlContent, UrlLoader,
+};
 use getopts::Options;
 use rustls::{
     client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
@@ -30,18 +33,16 @@ fn main() {
     // draft --
     let mut draft = Draft::default();
     if let Some(v) = matches.opt_str("draft") {
-        let Ok(v) = usize::from_str(&v) else {
-            eprintln!("invalid draft: {v}");
-            eprintln!();
-            eprintln!("{}", opts.usage(BRIEF));
-            process::exit(1);
-        };
-        draft = match v {
-            4 => Draft::V4,
-            6 => Draft::V6,
-            7 => Draft::V7,
-            2019 => Draft::V2019_09,
-            2020 => Draft::V2020_12,
+        draft = match v.as_str() {
+            "3" => Draft::V3,
+            "4" => Draft::V4,
+            "6" => Draft::V6,
+            "7" => Draft::V7,
+            "2019" => Draft::V2019_09,
+            "2020" => Draft::V2020_12,
+            "next" => Draft::Next,
+            "openapi3.0" => Draft::OpenApi3_0,
+            "kubernetes" => Draft::Kubernetes,
             _ => {
                 eprintln!("invalid draft: {v}");
                 eprintln!();
@@ -54,7 +55,10 @@ fn main() {
     // output --
     let output = matches.opt_str("output");
     if let Some(o) = &output {
-        if !matches!(o.as_str(), "simple" | "alt" | "flag" | "basic" | "detailed") {
+        if !matches!(
+            o.as_str(),
+            "simple" | "alt" | "flag" | "basic" | "detailed" | "list" | "hierarchical"
+        ) {
             eprintln!("invalid output: {o}");
             eprintln!();
             eprintln!("{}", opts.usage(BRIEF));
@@ -62,24 +66,66 @@ fn main() {
         }
     }
 
+    // format --
+    let mut format = None;
+    if let Some(f) = matches.opt_str("format") {
+        format = SourceFormat::from_extension(&f);
+        #[cfg(not(feature = "yaml"))]
+        if format == Some(SourceFormat::Yaml) {
+            format = None;
+        }
+        if format.is_none() {
+            eprintln!("invalid format: {f}");
+            eprintln!();
+            eprintln!("{}", opts.usage(BRIEF));
+            process::exit(1);
+        }
+    }
+
     // flags --
     let quiet = matches.opt_present("quiet");
     let assert_format = matches.opt_present("assert-format");
     let assert_content = matches.opt_present("assert-content");
     let insecure = matches.opt_present("insecure");
 
+    // catalog --
+    let catalog = matches.opt_str("catalog").map(|path| {
+        Catalog::from_file(&path).unwrap_or_else(|e| {
+            eprintln!("error loading catalog {path}: {e}");
+            process::exit(1);
+        })
+    });
+
     // schema --
-    let Some(schema) = matches.free.first() else {
-        eprintln!("missing SCHEMA");
-        eprintln!();
-        eprintln!("{}", opts.usage(BRIEF));
-        process::exit(1);
+    let (schema, instances) = match (&catalog, matches.free.split_first()) {
+        (Some(_), _) => (None, &matches.free[..]),
+        (None, Some((schema, instances))) => (Some(schema), instances),
+        (None, None) => {
+            eprintln!("missing SCHEMA");
+            eprintln!();
+            eprintln!("{}", opts.usage(BRIEF));
+            process::exit(1);
+        }
     };
 
     // compile --
     let mut schemas = Schemas::new();
     let mut compiler = Compiler::new();
-    compiler.register_url_loader("file", Box::new(FileUrlLoader));
+    if let Some(format) = format {
+        compiler.set_source_format(format);
+    }
+    for mapping in matches.opt_strs("map-url") {
+        let Some((prefix, dir)) = mapping.split_once('=') else {
+            eprintln!("invalid map-url: {mapping}");
+            eprintln!();
+            eprintln!("{}", opts.usage(BRIEF));
+            process::exit(1);
+        };
+        if let Err(e) = compiler.map_url_prefix(prefix, dir) {
+            eprintln!("invalid map-url: {mapping}: {e}");
+            process::exit(1);
+        }
+    }
     compiler.register_url_loader("http", Box::new(HttpUrlLoader::new(insecure)));
     compiler.register_url_loader("https", Box::new(HttpUrlLoader::new(insecure)));
     compiler.set_default_draft(draft);
@@ -89,28 +135,42 @@ fn main() {
     if assert_content {
         compiler.enable_content_assertions();
     }
-    let sch = match compiler.compile(schema, &mut schemas) {
-        Ok(sch) => {
-            println!("schema {schema}: ok");
-            sch
-        }
-        Err(e) => {
-            println!("schema {schema}: failed");
-            if !quiet {
-                println!("{e:#}");
-            }
+    let mut sch = None;
+    if let Some(schema) = schema {
+        let Some(index) = compile(&mut compiler, schema, &mut schemas, quiet) else {
             process::exit(2);
-        }
-    };
+        };
+        sch = Some(index);
+    }
+    let mut catalog_schemas = HashMap::new(); // url => compiled
 
     // validate --
     let mut all_valid = true;
-    for instance in &matches.free[1..] {
+    for instance in instances {
         if !quiet {
             println!();
         }
-        let rdr = match File::open(instance) {
-            Ok(rdr) => BufReader::new(rdr),
+        let sch = match (sch, &catalog) {
+            (Some(sch), _) => sch,
+            (None, Some(catalog)) => {
+                let Some(entry) = catalog.find(instance) else {
+                    println!("instance {instance}: skipped, no schema in catalog");
+                    continue;
+                };
+                let compiled = catalog_schemas
+                    .entry(entry.url.clone())
+                    .or_insert_with(|| compile(&mut compiler, &entry.url, &mut schemas, quiet));
+                let Some(sch) = *compiled else {
+                    println!("instance {instance}: failed");
+                    all_valid = false;
+                    continue;
+                };
+                sch
+            }
+            (None, None) => unreachable!("schema is required without catalog"),
+        };
+        let text = match fs::read_to_string(instance) {
+            Ok(text) => text,
             Err(e) => {
                 println!("instance {instance}: failed");
                 if !quiet {
@@ -120,13 +180,7 @@ fn main() {
                 continue;
             }
         };
-        let value: Result<Value, String> =
-            if instance.ends_with(".yaml") || instance.ends_with(".yml") {
-                serde_yaml::from_reader(rdr).map_err(|e| e.to_string())
-            } else {
-                serde_json::from_reader(rdr).map_err(|e| e.to_string())
-            };
-        let value = match value {
+        let (value, source_map) = match parse(Path::new(instance), &text, format) {
             Ok(v) => v,
             Err(e) => {
                 println!("instance {instance}: failed");
@@ -142,6 +196,7 @@ fn main() {
             Err(e) => {
                 println!("instance {instance}: failed");
                 if !quiet {
+                    let e = e.with_source_map(&source_map);
                     match &output {
                         Some(out) => match out.as_str() {
                             "simple" => println!("{e}"),
@@ -149,6 +204,8 @@ fn main() {
                             "flag" => println!("{:#}", e.flag_output()),
                             "basic" => println!("{:#}", e.basic_output()),
                             "detailed" => println!("{:#}", e.detailed_output()),
+                            "list" => println!("{:#}", e.list_output()),
+                            "hierarchical" => println!("{:#}", e.hierarchical_output()),
                             _ => (),
                         },
                         None => println!("{e}"),
@@ -164,7 +221,33 @@ fn main() {
     }
 }
 
-const BRIEF: &str = "Usage: boon [OPTIONS] SCHEMA [INSTANCE...]";
+fn compile(
+    compiler: &mut Compiler,
+    schema: &str,
+    schemas: &mut Schemas,
+    quiet: bool,
+) -> Option<SchemaIndex> {
+    match compiler.compile(schema, schemas) {
+        Ok(sch) => {
+            println!("schema {schema}: ok");
+            Some(sch)
+        }
+        Err(e) => {
+            println!("schema {schema}: failed");
+            if !quiet {
+                print_compile_error(compiler, &e);
+            }
+            None
+        }
+    }
+}
+
+#[cfg(feature = "yaml")]
+const SOURCE_FORMATS: &str = "json, jsonc, json5, toml, yaml";
+#[cfg(not(feature = "yaml"))]
+const SOURCE_FORMATS: &str = "json, jsonc, json5, toml";
+
+const BRIEF: &str = "Usage: boon [OPTIONS] SCHEMA [INSTANCE...]\n       boon [OPTIONS] --catalog <FILE> [INSTANCE...]";
 
 fn options() -> Options {
     let mut opts = Options::new();
@@ -173,13 +256,19 @@ fn options() -> Options {
     opts.optopt(
         "d",
         "draft",
-        "Draft used when '$schema' is missing. Valid values 4, 6, 7, 2019, 2020 (default 2020)",
+        "Draft used when '$schema' is missing. Valid values 3, 4, 6, 7, 2019, 2020, next, openapi3.0, kubernetes (default 2020)",
         "<VER>",
     );
     opts.optopt(
         "o",
         "output",
-        "Output format. Valid values simple, alt, flag, basic, detailed (default simple)",
+        "Output format. Valid values simple, alt, flag, basic, detailed, list, hierarchical (default simple)",
+        "<FMT>",
+    );
+    opts.optopt(
+        "",
+        "format",
+        &format!("Format of schema and instance files. Valid values {SOURCE_FORMATS} (default by file extension)"),
         "<FMT>",
     );
     opts.optflag(
@@ -192,28 +281,66 @@ fn options() -> Options {
         "assert-content",
         "Enable content assertions with draft >= 7",
     );
+    opts.optmulti(
+        "m",
+        "map-url",
+        "Load urls starting with PREFIX from DIR. Can be repeated",
+        "<PREFIX=DIR>",
+    );
+    opts.optopt(
+        "",
+        "catalog",
+        "SchemaStore catalog used to select schema of each instance by its file name",
+        "<FILE>",
+    );
     opts.optflag("k", "insecure", "Use insecure TLS connection");
     opts
 }
 
-struct FileUrlLoader;
-impl UrlLoader for FileUrlLoader {
-    fn load(&self, url: &str) -> Result<Value, Box<dyn Error>> {
-        let url = Url::parse(url)?;
-        let path = url.to_file_path().map_err(|_| "invalid file path")?;
-        let file = File::open(&path)?;
-        if path
-            .extension()
-            .filter(|&ext| ext == "yaml" || ext == "yml")
-            .is_some()
-        {
-            Ok(serde_yaml::from_reader(file)?)
-        } else {
-            Ok(serde_json::from_reader(file)?)
-        }
+fn print_compile_error(compiler: &Compiler, e: &CompileError) {
+    let position = match e {
+        CompileError::LoadUrlError { url, src } => src
+            .downcast_ref::<ParseError>()
+            .and_then(|e| Some((url.clone(), e.position?))),
+        _ => e
+            .location()
+            .and_then(|loc| Some((loc.clone(), compiler.position(&loc)?))),
+    };
+    if let Some((loc, pos)) = position {
+        let (file, _) = loc.split_once('#').unwrap_or((&loc, ""));
+        let file = Url::parse(file)
+            .ok()
+            .and_then(|u| u.to_file_path().ok())
+            .map(|p| p.display().to_string())
+            .unwrap_or(file.to_owned());
+        print!("{file}:{pos}: ");
+    }
+    match e {
+        CompileError::ValidationError { url, src } => match compiler.source_map(url) {
+            Some(source_map) => println!(
+                "{url} is not valid against metaschema: {}",
+                src.with_source_map(&source_map)
+            ),
+            None => println!("{e:#}"),
+        },
+        _ => println!("{e:#}"),
     }
 }
 
+fn parse(
+    path: &Path,
+    text: &str,
+    format: Option<SourceFormat>,
+) -> Result<(Value, SourceMap), ParseError> {
+    let format = format
+        .or_else(|| {
+            let ext = path.extension()?.to_str()?;
+            SourceFormat::from_extension(ext)
+        })
+        .unwrap_or(SourceFormat::Json);
+    SourceMap::parse(text, format)
+}
+
 struct HttpUrlLoader(Agent);
 
 impl HttpUrlLoader {
@@ -231,17 +358,12 @@ impl HttpUrlLoader {
 }
 
 impl UrlLoader for HttpUrlLoader {
-    fn load(&self, url: &str) -> Result<Value, Box<dyn Error>> {
+    fn load_content(&self, url: &str) -> Result<Option<UrlContent>, Box<dyn Error>> {
         let response = self.0.get(url).call()?;
-        let is_yaml = url.ends_with(".yaml") || url.ends_with(".yml") || {
-            let ctype = response.content_type();
-            ctype.ends_with("/yaml") || ctype.ends_with("-yaml")
-        };
-        if is_yaml {
-            Ok(serde_yaml::from_reader(response.into_reader())?)
-        } else {
-            Ok(serde_json::from_reader(response.into_reader())?)
-        }
+        let media_type = response.content_type().to_owned();
+        let mut bytes = vec![];
+        response.into_reader().read_to_end(&mut bytes)?;
+        Ok(Some(UrlContent::new(bytes).with_media_type(media_type)))
     }
 }
 
@@ -0,0 +1,126 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use url::Url;
+
+use crate::{loader::Document, SourceFormat};
+
+/**
+Cache of documents loaded by [`UrlLoader`]s, which can be shared by
+multiple [`Compiler`]s, even across threads.
+
+Each document is loaded and parsed once, and reused by all compilers
+using the cache. Cloning the cache is cheap, and the clones share the
+same documents.
+
+```
+# use boon::*;
+let cache = DocumentCache::with_limit(100);
+let mut c1 = Compiler::new();
+c1.set_document_cache(cache.clone());
+let mut c2 = Compiler::new();
+c2.set_document_cache(cache.clone());
+```
+
+[`UrlLoader`]: crate::UrlLoader
+[`Compiler`]: crate::Compiler
+*/
+#[derive(Debug, Clone, Default)]
+pub struct DocumentCache {
+    inner: Arc<Mutex<Inner>>,
+}
+
+// documents are parsed differently when format is overridden
+type Key = (Url, Option<SourceFormat>);
+
+#[derive(Debug, Default)]
+struct Inner {
+    docs: HashMap<Key, (Arc<Document>, u64)>, // key => (doc, last used tick)
+    order: BTreeMap<u64, Key>,                // least recently used first
+    tick: u64,
+    limit: Option<usize>,
+}
+
+impl DocumentCache {
+    /// Creates cache without size limit.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates cache holding at most `limit` documents. When full,
+    /// least recently used document is evicted.
+    pub fn with_limit(limit: usize) -> Self {
+        let cache = Self::default();
+        cache.lock().limit = Some(limit);
+        cache
+    }
+
+    /// Returns number of documents in cache.
+    pub fn len(&self) -> usize {
+        self.lock().docs.len()
+    }
+
+    /// Returns true if cache has no documents.
+    pub fn is_empty(&self) -> bool {
+        self.lock().docs.is_empty()
+    }
+
+    /// Removes all documents.
+    pub fn clear(&self) {
+        let mut inner = self.lock();
+        inner.docs.clear();
+        inner.order.clear();
+    }
+
+    // removes documents of `url`, parsed in any format
+    pub(crate) fn remove(&self, url: &Url) {
+        let inner = &mut *self.lock();
+        let order = &mut inner.order;
+        inner.docs.retain(|(u, _), (_, tick)| {
+            if u == url {
+                order.remove(tick);
+            }
+            u != url
+        });
+    }
+
+    pub(crate) fn get(&self, url: &Url, format: Option<SourceFormat>) -> Option<Arc<Document>> {
+        let mut inner = self.lock();
+        let key = (url.clone(), format);
+        let doc = Arc::clone(&inner.docs.get(&key)?.0);
+        inner.touch(key, Arc::clone(&doc));
+        Some(doc)
+    }
+
+    pub(crate) fn insert(&self, url: Url, format: Option<SourceFormat>, doc: Arc<Document>) {
+        let mut inner = self.lock();
+        if inner.limit == Some(0) {
+            return;
+        }
+        inner.touch((url, format), doc);
+        while inner.limit.is_some_and(|limit| inner.docs.len() > limit) {
+            let Some((_, key)) = inner.order.pop_first() else {
+                break;
+            };
+            inner.docs.remove(&key);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        // cache is consistent even if other thread panicked
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Inner {
+    // stores `doc` at `key`, marking it as most recently used
+    fn touch(&mut self, key: Key, doc: Arc<Document>) {
+        self.tick += 1;
+        if let Some((_, tick)) = self.docs.insert(key.clone(), (doc, self.tick)) {
+            self.order.remove(&tick);
+        }
+        self.order.insert(self.tick, key);
+    }
+}
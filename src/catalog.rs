@@ -0,0 +1,239 @@
+use std::error::Error;
+
+use serde_json::Value;
+
+use crate::{compiler::CompileError, util::*, Compiler, SchemaIndex, Schemas};
+
+/**
+Catalog of schemas in the format of [SchemaStore] `catalog.json`,
+used to select schema of a file by its path.
+
+```
+# use boon::*;
+# use serde_json::json;
+let catalog = Catalog::from_value(&json!({
+    "schemas": [{
+        "name": "package.json",
+        "url": "https://json.schemastore.org/package.json",
+        "fileMatch": ["package.json"]
+    }]
+})).unwrap();
+let entry = catalog.find("web/package.json").unwrap();
+assert_eq!(entry.url, "https://json.schemastore.org/package.json");
+```
+
+[SchemaStore]: https://www.schemastore.org/
+*/
+#[derive(Debug, Default, Clone)]
+pub struct Catalog {
+    pub entries: Vec<CatalogEntry>,
+}
+
+/// Schema in [`Catalog`].
+#[derive(Debug, Clone)]
+pub struct CatalogEntry {
+    pub name: String,
+    /// Location of schema.
+    pub url: String,
+    /**
+    Glob patterns of files validated by this schema.
+
+    Patterns without `/` are matched against file name, others against
+    trailing segments of file path. `*` and `?` do not match `/`, `**`
+    matches any number of segments and `{a,b}` matches either alternative.
+    Patterns starting with `!` exclude matching files.
+    */
+    pub file_match: Vec<String>,
+}
+
+impl Catalog {
+    /**
+    Loads catalog from json file at `path`.
+
+    Relative schema urls in catalog are resolved against `path`.
+    */
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_file(path: &str) -> Result<Catalog, Box<dyn Error>> {
+        let text = std::fs::read_to_string(path)?;
+        let mut catalog = Self::from_value(&serde_json::from_str(&text)?)?;
+        let base = UrlFrag::absolute(path)?.url;
+        for entry in &mut catalog.entries {
+            if let Ok(url) = base.join(&entry.url) {
+                entry.url = url.to_string();
+            }
+        }
+        Ok(catalog)
+    }
+
+    /// Parses catalog from json `value`. Entries without `fileMatch` are ignored.
+    pub fn from_value(value: &Value) -> Result<Catalog, Box<dyn Error>> {
+        let Some(Value::Array(schemas)) = value.get("schemas") else {
+            return Err("catalog must have schemas array".into());
+        };
+        let mut entries = vec![];
+        for sch in schemas {
+            let Some(Value::String(url)) = sch.get("url") else {
+                return Err("catalog schema must have url".into());
+            };
+            let Some(Value::Array(file_match)) = sch.get("fileMatch") else {
+                continue;
+            };
+            let name = match sch.get("name") {
+                Some(Value::String(name)) => name.clone(),
+                _ => url.clone(),
+            };
+            let file_match = file_match
+                .iter()
+                .filter_map(|p| p.as_str().map(str::to_owned))
+                .collect();
+            entries.push(CatalogEntry {
+                name,
+                url: url.clone(),
+                file_match,
+            });
+        }
+        Ok(Catalog { entries })
+    }
+
+    /// Returns first entry whose `fileMatch` matches file `path`.
+    pub fn find(&self, path: &str) -> Option<&CatalogEntry> {
+        let path = path.replace('\\', "/");
+        self.entries.iter().find(|e| e.matches(&path))
+    }
+
+    /**
+    Compiles schema selected for file `path` into `target`.
+
+    Returns `None` if no entry matches `path`.
+    */
+    pub fn compile(
+        &self,
+        compiler: &mut Compiler,
+        path: &str,
+        target: &mut Schemas,
+    ) -> Result<Option<SchemaIndex>, CompileError> {
+        match self.find(path) {
+            Some(entry) => compiler.compile(&entry.url, target).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+impl CatalogEntry {
+    fn matches(&self, path: &str) -> bool {
+        let mut matched = false;
+        for pattern in &self.file_match {
+            match pattern.strip_prefix('!') {
+                Some(pattern) if glob_matches(pattern, path) => return false,
+                Some(_) => {}
+                None => matched = matched || glob_matches(pattern, path),
+            }
+        }
+        matched
+    }
+}
+
+fn glob_matches(pattern: &str, path: &str) -> bool {
+    let pattern = pattern.strip_prefix("./").unwrap_or(pattern);
+    let candidates: Vec<&str> = if pattern.contains('/') {
+        // pattern can match trailing segments of path
+        let mut v = vec![path];
+        v.extend(path.match_indices('/').map(|(i, _)| &path[i + 1..]));
+        v
+    } else {
+        vec![path.rsplit('/').next().unwrap_or(path)]
+    };
+    let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+    expand_braces(pattern).iter().any(|p| {
+        let p: Vec<char> = p.chars().collect();
+        candidates.iter().any(|c| {
+            let c: Vec<char> = c.chars().collect();
+            wildcard_matches(&p, &c)
+        })
+    })
+}
+
+// expands `{a,b}` into alternatives
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let Some(start) = pattern.find('{') else {
+        return vec![pattern.to_owned()];
+    };
+    let Some(len) = pattern[start..].find('}') else {
+        return vec![pattern.to_owned()];
+    };
+    let (prefix, alts, suffix) = (
+        &pattern[..start],
+        &pattern[start + 1..start + len],
+        &pattern[start + len + 1..],
+    );
+    let suffixes = expand_braces(suffix);
+    let mut v = vec![];
+    for alt in alts.split(',') {
+        for suffix in &suffixes {
+            v.push(format!("{prefix}{alt}{suffix}"));
+        }
+    }
+    v
+}
+
+fn wildcard_matches(pattern: &[char], path: &[char]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            // zero or more segments
+            wildcard_matches(rest, path)
+                || (0..path.len())
+                    .filter(|&i| path[i] == '/')
+                    .any(|i| wildcard_matches(rest, &path[i + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=path.len()).any(|i| wildcard_matches(rest, &path[i..])),
+        ['*', rest @ ..] => {
+            let seg = path.iter().position(|&c| c == '/').unwrap_or(path.len());
+            (0..=seg).any(|i| wildcard_matches(rest, &path[i..]))
+        }
+        ['?', rest @ ..] => {
+            matches!(path.first(), Some(c) if *c != '/') && wildcard_matches(rest, &path[1..])
+        }
+        [c, rest @ ..] => path.first() == Some(c) && wildcard_matches(rest, &path[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_matches() {
+        let tests = [
+            ("package.json", "package.json", true),
+            ("package.json", "web/package.json", true),
+            ("package.json", "web/package.json.bak", false),
+            ("*.eslintrc.json", "a/.eslintrc.json", true),
+            ("tsconfig.*.json", "tsconfig.base.json", true),
+            ("*.json", "a/b.json", true),
+            ("*.{yml,yaml}", "ci.yaml", true),
+            (
+                ".github/workflows/*.yml",
+                "repo/.github/workflows/ci.yml",
+                true,
+            ),
+            (".github/workflows/*.yml", "repo/.github/ci.yml", false),
+            (
+                "**/.github/workflows/*.yml",
+                ".github/workflows/ci.yml",
+                true,
+            ),
+            (
+                "/.github/workflows/*.yml",
+                "repo/.github/workflows/ci.yml",
+                true,
+            ),
+            ("docs/**/*.json", "docs/a/b/c.json", true),
+            ("docs/**/*.json", "docs/c.json", true),
+            ("?.json", "ab.json", false),
+        ];
+        for (pattern, path, want) in tests {
+            assert_eq!(glob_matches(pattern, path), want, "{pattern} {path}");
+        }
+    }
+}
@@ -1,6 +1,11 @@
-use std::{cmp::Ordering, collections::HashMap, error::Error, fmt::Display};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt::Display,
+    sync::Arc,
+};
 
-use regex::Regex;
 use serde_json::{Map, Value};
 use url::Url;
 
@@ -10,6 +15,8 @@ use crate::{content::*, draft::*, ecma, formats::*, root::*, roots::*, util::*,
 #[non_exhaustive]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Draft {
+    /// Draft for `http://json-schema.org/draft-03/schema`
+    V3,
     /// Draft for `http://json-schema.org/draft-04/schema`
     V4,
     /// Draft for `http://json-schema.org/draft-06/schema`
@@ -20,6 +27,30 @@ pub enum Draft {
     V2019_09,
     /// Draft for `https://json-schema.org/draft/2020-12/schema`
     V2020_12,
+    /// Draft for `https://json-schema.org/draft/next/schema`
+    ///
+    /// This tracks the upcoming release of the specification,
+    /// and its behavior may change in future versions of this library.
+    Next,
+    /// Schema object of OpenAPI 3.0
+    ///
+    /// This is draft-04 extended with `nullable`, `discriminator`
+    /// and the formats `int32`, `int64`, `float`, `double`, `byte` and `binary`.
+    /// Properties marked `readOnly` or `writeOnly` are not enforced
+    /// by `required`, since they are only required in one direction.
+    /// When a `discriminator` sits next to `oneOf` or `anyOf`, its property
+    /// selects the subschema the value is validated against. Schema names
+    /// in its `mapping` refer to `components/schemas` of the document.
+    OpenApi3_0,
+    /// Kubernetes structural schema, as in `openAPIV3Schema` of CRDs
+    ///
+    /// This is [`Draft::OpenApi3_0`] extended with `x-kubernetes-int-or-string`,
+    /// `x-kubernetes-preserve-unknown-fields`, `x-kubernetes-embedded-resource`,
+    /// `x-kubernetes-list-type` and `x-kubernetes-list-map-keys`.
+    /// Unknown fields of objects are rejected, unless preserved.
+    /// The schema being compiled and embedded resources require
+    /// `apiVersion` and `kind`, and allow `metadata`.
+    Kubernetes,
 }
 
 impl Draft {
@@ -41,24 +72,61 @@ impl Draft {
     pub fn from_url(url: &str) -> Option<Draft> {
         match crate::draft::Draft::from_url(url) {
             Some(draft) => match draft.version {
+                3 => Some(Draft::V3),
                 4 => Some(Draft::V4),
                 6 => Some(Draft::V6),
                 7 => Some(Draft::V7),
                 2019 => Some(Draft::V2019_09),
                 2020 => Some(Draft::V2020_12),
+                VERSION_NEXT => Some(Draft::Next),
                 _ => None,
             },
             None => None,
         }
     }
 
+    /**
+    Convert schema document `doc` into draft 2020-12.
+
+    The draft of `doc` is determined from its `$schema`; if `$schema`
+    is missing or unknown, `self` is assumed. Documents of [`Draft::Next`]
+    are returned unchanged. Keywords are rewritten to their
+    2020-12 equivalents (`definitions` to `$defs`, array-form `items` to
+    `prefixItems`, `dependencies` to `dependentRequired`/`dependentSchemas`,
+    `$recursiveRef` to `$dynamicRef` etc) and json-pointers in `$ref` into
+    the same document are adjusted to point into the converted document.
+
+    `$recursiveAnchor` is translated into `$dynamicAnchor` named `meta`.
+    It is an error, if `doc` already uses that anchor.
+
+    # Examples
+
+    ```
+    # use boon::*;
+    # use serde_json::json;
+    # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let doc = json!({"definitions": {"a": {}}, "$ref": "#/definitions/a"});
+    let doc = Draft::V7.upgrade(&doc)?;
+    assert_eq!(doc["$ref"], "#/$defs/a");
+    # Ok(())
+    # }
+    ```
+    */
+    pub fn upgrade(&self, doc: &Value) -> Result<Value, UpgradeError> {
+        self.internal().upgrade(doc)
+    }
+
     pub(crate) fn internal(&self) -> &'static crate::draft::Draft {
         match self {
+            Draft::V3 => &DRAFT3,
             Draft::V4 => &DRAFT4,
             Draft::V6 => &DRAFT6,
             Draft::V7 => &DRAFT7,
             Draft::V2019_09 => &DRAFT2019,
             Draft::V2020_12 => &DRAFT2020,
+            Draft::Next => &DRAFT_NEXT,
+            Draft::OpenApi3_0 => &OPENAPI30,
+            Draft::Kubernetes => &KUBERNETES,
         }
     }
 }
@@ -73,12 +141,15 @@ impl Default for Draft {
 /// JsonSchema compiler.
 #[derive(Default)]
 pub struct Compiler {
-    roots: Roots,
+    pub(crate) roots: Roots,
     assert_format: bool,
     assert_content: bool,
     formats: HashMap<&'static str, Format>,
     decoders: HashMap<&'static str, Decoder>,
     media_types: HashMap<&'static str, MediaType>,
+    regex_limits: RegexLimits,
+    regex_engine: Option<Arc<dyn RegexEngine>>,
+    changed: HashSet<Url>, // invalidated documents, to be reloaded
 }
 
 impl Compiler {
@@ -133,7 +204,8 @@ impl Compiler {
     Registers [`UrlLoader`] for given url `scheme`
 
     # Note
-    - loader for `file` scheme is included by default and
+    - loader for `file` scheme is included by default, use [`SandboxedFileLoader`]
+      to restrict it to a directory and
     - all standard meta-schemas from `http(s)://json-schema.org` are loaded internally
       without network access
     */
@@ -141,6 +213,85 @@ impl Compiler {
         self.roots.loader.register(scheme, url_loader);
     }
 
+    /**
+    Loads documents whose url starts with `prefix` from `dir`, which
+    can be file path or url.
+
+    This is useful to resolve published `$id`s to local copies of
+    schemas, without network access. The documents keep their original
+    urls, so that `$id`s and references resolve as usual. Prefix matches
+    only at segment boundary, so `https://example.com/v1` does not match
+    `https://example.com/v10/`. When several prefixes match, the longest
+    one is used.
+
+    # Examples
+
+    ```
+    # use boon::*;
+    let mut compiler = Compiler::new();
+    compiler.map_url_prefix("https://schemas.example.com/", "/opt/schemas/")?;
+    # Ok::<(), CompileError>(())
+    ```
+
+    # Errors
+
+    returns [`CompileError`] if `dir` parsing failed.
+    */
+    pub fn map_url_prefix(&mut self, prefix: &str, dir: &str) -> Result<(), CompileError> {
+        let uf = UrlFrag::absolute(dir)?;
+        self.roots.loader.map_url_prefix(prefix.to_owned(), uf.url);
+        Ok(())
+    }
+
+    /**
+    Uses `cache` for documents loaded by [`UrlLoader`]s, so that they are
+    loaded and parsed once across all compilers sharing the cache.
+
+    Documents added with [`Compiler::add_resource`] and standard
+    meta-schemas are not cached.
+    */
+    pub fn set_document_cache(&mut self, cache: DocumentCache) {
+        self.roots.loader.set_cache(cache);
+    }
+
+    /**
+    Limits regexes of `pattern` and `patternProperties` in schemas compiled
+    later. Regexes exceeding them fail to compile with
+    [`CompileError::InvalidRegex`].
+
+    By default there are no limits on pattern length and number of regexes,
+    see [`RegexLimits::default`].
+    */
+    pub fn set_regex_limits(&mut self, limits: RegexLimits) {
+        self.regex_limits = limits;
+    }
+
+    /**
+    Sets [`RegexEngine`] used for `pattern`, `patternProperties` and format
+    `regex` in schemas compiled later.
+
+    By default [`FancyRegexEngine`] is used with feature `fancy-regex`,
+    otherwise [`StdRegexEngine`].
+
+    [`FancyRegexEngine`]: crate::FancyRegexEngine
+    */
+    pub fn set_regex_engine(&mut self, engine: Box<dyn RegexEngine>) {
+        self.regex_engine = Some(Arc::from(engine));
+    }
+
+    fn regex_engine(&self) -> &Arc<dyn RegexEngine> {
+        self.regex_engine.as_ref().unwrap_or(&ecma::DEFAULT_ENGINE)
+    }
+
+    /**
+    Restricts documents loaded by [`UrlLoader`]s, for example to prevent
+    untrusted schemas from referencing local files. Loading documents
+    violating `policy` fails with [`CompileError::UrlNotAllowed`].
+    */
+    pub fn set_load_policy(&mut self, policy: LoadPolicy) {
+        self.roots.loader.set_policy(policy);
+    }
+
     /**
     Registers custom `format`
 
@@ -192,6 +343,85 @@ impl Compiler {
         Ok(())
     }
 
+    /**
+    Overrides the format of documents loaded from `file` urls, by any
+    loader registered for `file` scheme, and added with
+    [`Compiler::add_resource_text`].
+
+    By default the format is detected from file extension, and documents
+    without known extension are parsed as json.
+    */
+    pub fn set_source_format(&mut self, format: SourceFormat) {
+        self.roots.loader.set_source_format(format);
+    }
+
+    /**
+    Adds schema resource from its source `text`, which is used later in
+    reference resolution. Positions of its values are recorded to report
+    line and column of compile errors.
+
+    The format of `text` is detected from extension of `loc`, unless
+    overridden by [`Compiler::set_source_format`]; see [`SourceFormat`].
+    If yaml `text` has multiple documents, each document is added with
+    query `document=<index>` in `loc`; for example `schemas.yaml?document=1`.
+    Note that file paths have no query, use `file` url to refer to them.
+
+    # Errors
+
+    returns [`CompileError`] if url parsing or `text` parsing failed.
+    */
+    pub fn add_resource_text(&mut self, loc: &str, text: &str) -> Result<(), CompileError> {
+        let uf = UrlFrag::absolute(loc)?;
+        self.roots.loader.add_resource_text(uf.url, text)
+    }
+
+    /**
+    Returns line and column of the value at `loc`, in the source text
+    of its document.
+
+    Positions are recorded only for documents loaded by a [`UrlLoader`]
+    which implements [`UrlLoader::load_with_source_map`], such as the
+    default loader for `file` urls.
+
+    # Examples
+
+    ```no_run
+    # use boon::*;
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    if let Err(e) = compiler.compile("schema.json", &mut schemas) {
+        match e.location().and_then(|loc| compiler.position(&loc)) {
+            Some(pos) => eprintln!("schema.json:{pos}: {e:#}"),
+            None => eprintln!("{e:#}"),
+        }
+    }
+    ```
+    */
+    pub fn position(&self, loc: &str) -> Option<Position> {
+        let up = self.locate(loc)?;
+        self.roots.loader.position(&up.url, up.ptr.as_str())
+    }
+
+    /**
+    Returns positions of the value at `loc` and its descendants, in
+    the source text of its document.
+
+    This is useful to report positions of [`CompileError::ValidationError`],
+    whose instance locations are relative to the invalid schema.
+    */
+    pub fn source_map(&self, loc: &str) -> Option<SourceMap> {
+        let up = self.locate(loc)?;
+        self.roots.loader.source_map(&up.url, up.ptr.as_str())
+    }
+
+    fn locate(&self, loc: &str) -> Option<UrlPtr> {
+        let uf = UrlFrag::absolute(loc).ok()?;
+        match uf.frag {
+            Fragment::JsonPointer(ptr) => Some(UrlPtr { url: uf.url, ptr }),
+            Fragment::Anchor(_) => self.roots.get(&uf.url)?.resolve_fragment(&uf.frag).ok(),
+        }
+    }
+
     /**
     Compile given `loc` into `target` and return an identifier to the compiled
     schema.
@@ -212,12 +442,153 @@ impl Compiler {
         let up = self.roots.resolve_fragment(uf)?;
 
         let result = self.do_compile(up, target);
-        if let Err(bug @ CompileError::Bug(_)) = &result {
-            debug_assert!(false, "{bug}");
+        match &result {
+            Ok(index) => target.add_root(*index),
+            Err(bug @ CompileError::Bug(_)) => debug_assert!(false, "{bug}"),
+            Err(_) => {}
         }
         result
     }
 
+    /**
+    Unloads documents which have no schema compiled in `schemas`, typically
+    after [`Schemas::remove`]. Unloaded documents are loaded again, if they
+    are needed by later compilations.
+
+    Documents added with [`Compiler::add_resource`] are kept.
+    */
+    pub fn unload_unused(&mut self, schemas: &Schemas) {
+        let used = schemas.urls();
+        self.roots.retain(|url| used.contains(url));
+    }
+
+    /**
+    Validates schema document at `loc` against its metaschema, without
+    compiling it or loading documents it references.
+
+    The returned [`ValidationError`] has all metaschema violations of the
+    document, and can be reported using any of its output formats. Use
+    [`Compiler::source_map`] to get their positions.
+
+    The argument `loc` can be file path or url. any fragment in `loc` is
+    ignored.
+
+    # Errors
+
+    returns [`CompileError`] if document could not be loaded, or its
+    metaschema could not be determined.
+
+    ```
+    # use boon::*;
+    # use serde_json::json;
+    # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut compiler = Compiler::new();
+    compiler.add_resource(
+        "schema.json",
+        json!({"type": 1, "minLength": "2"}),
+    )?;
+    let Err(e) = compiler.validate_schema("schema.json")? else {
+        panic!("schema must be invalid");
+    };
+    // both violations are reported
+    let msg = format!("{e:#}");
+    assert!(msg.contains("'/type'") && msg.contains("'/minLength'"));
+    # Ok(())
+    # }
+    ```
+    */
+    pub fn validate_schema(
+        &mut self,
+        loc: &str,
+    ) -> Result<Result<(), ValidationError<'static, 'static>>, CompileError> {
+        let uf = UrlFrag::absolute(loc)?;
+        self.roots.validate(&uf.url)
+    }
+
+    /**
+    Marks document at `loc` as changed. It is loaded again by later
+    compilations, and schemas compiled from it are recompiled by
+    [`Compiler::reload`].
+
+    The argument `loc` can be file path or url. any fragment in `loc` is
+    ignored. If `loc` is a multi-document file without `document` query,
+    all its documents are marked as changed.
+
+    # Errors
+
+    returns [`CompileError`] if url parsing failed.
+    */
+    pub fn invalidate(&mut self, loc: &str) -> Result<(), CompileError> {
+        let uf = UrlFrag::absolute(loc)?;
+        let urls = self.roots.invalidate(&uf.url)?;
+        self.changed.extend(urls);
+        Ok(())
+    }
+
+    /**
+    Marks files modified since they were loaded as changed, and returns
+    their urls. See [`Compiler::invalidate`].
+    */
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn invalidate_modified(&mut self) -> Result<Vec<String>, CompileError> {
+        let mut modified = vec![];
+        for url in self.roots.loader.modified_files() {
+            self.invalidate(url.as_str())?;
+            modified.push(url.into());
+        }
+        Ok(modified)
+    }
+
+    /**
+    Recompiles schemas in `target` which depend on documents marked as
+    changed, and returns pairs of their old and new [`SchemaIndex`].
+
+    Old schemas stay valid, so that validations in progress are not
+    affected. Remove them with [`Schemas::remove`] once they are no longer
+    used. If any schema fails to compile, `target` is left unchanged and
+    documents remain marked as changed.
+
+    ```no_run
+    # use boon::*;
+    # fn main() -> Result<(), CompileError> {
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    let mut sch = compiler.compile("schema.json", &mut schemas)?;
+    // on file change
+    compiler.invalidate_modified()?;
+    for (old, new) in compiler.reload(&mut schemas)? {
+        if old == sch {
+            sch = new;
+        }
+        schemas.remove(old);
+    }
+    # Ok(())
+    # }
+    ```
+    */
+    pub fn reload(
+        &mut self,
+        target: &mut Schemas,
+    ) -> Result<Vec<(SchemaIndex, SchemaIndex)>, CompileError> {
+        let (roots, detached) = target.detach(&self.changed);
+        let mut reloaded = vec![];
+        for old in roots {
+            let loc = target.get(old).loc.clone();
+            match self.compile(&loc, target) {
+                Ok(new) => reloaded.push((old, new)),
+                Err(e) => {
+                    for (_, new) in reloaded {
+                        target.remove(new);
+                    }
+                    target.attach(detached);
+                    return Err(e);
+                }
+            }
+        }
+        self.changed.clear();
+        Ok(reloaded)
+    }
+
     fn do_compile(
         &mut self,
         up: UrlPtr,
@@ -244,6 +615,7 @@ impl Compiler {
             self.roots.insert(&mut queue.roots);
         }
 
+        self.roots.add_regexes(queue.regexes);
         target.insert(queue.schemas, compiled);
         Ok(index)
     }
@@ -336,6 +708,18 @@ struct ObjCompiler<'c, 'v, 'l, 's, 'r, 'q> {
 impl<'c, 'v, 'l, 's, 'r, 'q> ObjCompiler<'c, 'v, 'l, 's, 'r, 'q> {
     fn compile_obj(&mut self, s: &mut Schema) -> Result<(), CompileError> {
         self.compile_draft4(s)?;
+        if self.draft_version() == 3 {
+            self.compile_draft3(s)?;
+        }
+        if self.root.draft.dialect == Dialect::Kubernetes {
+            self.compile_kubernetes(s)?;
+        }
+        if matches!(
+            self.root.draft.dialect,
+            Dialect::OpenApi30 | Dialect::Kubernetes
+        ) {
+            self.compile_openapi30(s)?;
+        }
         if self.draft_version() >= 6 {
             self.compile_draft6(s)?;
         }
@@ -348,6 +732,10 @@ impl<'c, 'v, 'l, 's, 'r, 'q> ObjCompiler<'c, 'v, 'l, 's, 'r, 'q> {
         if self.draft_version() >= 2020 {
             self.compile_draft2020(s)?;
         }
+        if self.draft_version() >= VERSION_NEXT {
+            self.compile_draft_next(s)?;
+        }
+        self.compile_error_message(s)?;
         Ok(())
     }
 
@@ -361,10 +749,12 @@ impl<'c, 'v, 'l, 's, 'r, 'q> ObjCompiler<'c, 'v, 'l, 's, 'r, 'q> {
         }
 
         if self.has_vocab("applicator") {
-            s.all_of = self.enqueue_arr("allOf");
-            s.any_of = self.enqueue_arr("anyOf");
-            s.one_of = self.enqueue_arr("oneOf");
-            s.not = self.enqueue_prop("not");
+            if self.draft_version() >= 4 {
+                s.all_of = self.enqueue_arr("allOf");
+                s.any_of = self.enqueue_arr("anyOf");
+                s.one_of = self.enqueue_arr("oneOf");
+                s.not = self.enqueue_prop("not");
+            }
 
             if self.draft_version() < 2020 {
                 match self.value("items") {
@@ -381,18 +771,7 @@ impl<'c, 'v, 'l, 's, 'r, 'q> ObjCompiler<'c, 'v, 'l, 's, 'r, 'q> {
                 let mut v = vec![];
                 if let Some(Value::Object(obj)) = self.value("patternProperties") {
                     for pname in obj.keys() {
-                        let ecma =
-                            ecma::convert(pname).map_err(|src| CompileError::InvalidRegex {
-                                url: self.up.format("patternProperties"),
-                                regex: pname.to_owned(),
-                                src,
-                            })?;
-                        let regex =
-                            Regex::new(ecma.as_ref()).map_err(|e| CompileError::InvalidRegex {
-                                url: self.up.format("patternProperties"),
-                                regex: ecma.into_owned(),
-                                src: e.into(),
-                            })?;
+                        let regex = self.regex("patternProperties", pname)?;
                         let ptr = self.up.ptr.append2("patternProperties", pname);
                         let sch = self.enqueue_schema(ptr);
                         v.push((regex, sch));
@@ -409,6 +788,7 @@ impl<'c, 'v, 'l, 's, 'r, 'q> ObjCompiler<'c, 'v, 'l, 's, 'r, 'q> {
                     .filter_map(|(k, v)| {
                         let v = match v {
                             Value::Array(_) => Some(Dependency::Props(to_strings(v))),
+                            Value::String(prop) => Some(Dependency::Props(vec![prop.clone()])),
                             _ => {
                                 let ptr = self.up.ptr.append2("dependencies", k);
                                 Some(Dependency::SchemaRef(self.enqueue_schema(ptr)))
@@ -450,7 +830,9 @@ impl<'c, 'v, 'l, 's, 'r, 'q> ObjCompiler<'c, 'v, 'l, 's, 'r, 'q> {
                 });
             }
 
-            s.multiple_of = self.num("multipleOf");
+            if self.draft_version() >= 4 {
+                s.multiple_of = self.num("multipleOf");
+            }
 
             s.maximum = self.num("maximum");
             if let Some(Value::Bool(exclusive)) = self.value("exclusiveMaximum") {
@@ -474,19 +856,20 @@ impl<'c, 'v, 'l, 's, 'r, 'q> ObjCompiler<'c, 'v, 'l, 's, 'r, 'q> {
             s.min_length = self.usize("minLength");
 
             if let Some(Value::String(p)) = self.value("pattern") {
-                let p = ecma::convert(p).map_err(CompileError::Bug)?;
-                s.pattern = Some(Regex::new(p.as_ref()).map_err(|e| CompileError::Bug(e.into()))?);
+                s.pattern = Some(self.regex("pattern", p)?);
             }
 
             s.max_items = self.usize("maxItems");
             s.min_items = self.usize("minItems");
             s.unique_items = self.bool("uniqueItems");
 
-            s.max_properties = self.usize("maxProperties");
-            s.min_properties = self.usize("minProperties");
+            if self.draft_version() >= 4 {
+                s.max_properties = self.usize("maxProperties");
+                s.min_properties = self.usize("minProperties");
 
-            if let Some(req) = self.value("required") {
-                s.required = to_strings(req);
+                if let Some(req) = self.value("required") {
+                    s.required = to_strings(req);
+                }
             }
         }
 
@@ -499,15 +882,201 @@ impl<'c, 'v, 'l, 's, 'r, 'q> ObjCompiler<'c, 'v, 'l, 's, 'r, 'q> {
             })
         {
             if let Some(Value::String(format)) = self.value("format") {
+                let format = match format.as_str() {
+                    "ip-address" if self.draft_version() == 3 => "ipv4",
+                    "host-name" if self.draft_version() == 3 => "hostname",
+                    format => format,
+                };
                 s.format = self
                     .c
                     .formats
-                    .get(format.as_str())
-                    .or_else(|| FORMATS.get(format.as_str()))
+                    .get(format)
+                    .or_else(|| FORMATS.get(format))
                     .cloned();
+                if format == "regex" {
+                    s.regex_engine = Some(Arc::clone(self.c.regex_engine()));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn compile_draft3(&mut self, s: &mut Schema) -> Result<(), CompileError> {
+        if s.ref_.is_some() {
+            // All other properties in a "$ref" object MUST be ignored
+            return Ok(());
+        }
+
+        (s.types, s.type_schemas) = self.enqueue_types("type");
+        (s.disallow, s.disallow_schemas) = self.enqueue_types("disallow");
+
+        s.extends = match self.value("extends") {
+            Some(Value::Array(_)) => self.enqueue_arr("extends"),
+            _ => self.enqueue_prop("extends").into_iter().collect(),
+        };
+
+        s.multiple_of = self.num("divisibleBy");
+
+        // required is specified as boolean in property schema
+        if let Some(Value::Object(props)) = self.value("properties") {
+            for (pname, pvalue) in props {
+                if let Value::Object(obj) = pvalue {
+                    if let Some(Value::Bool(true)) = obj.get("required") {
+                        s.required.push(pname.clone());
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn compile_openapi30(&mut self, s: &mut Schema) -> Result<(), CompileError> {
+        if s.ref_.is_some() {
+            // All other properties in a "$ref" object MUST be ignored
+            return Ok(());
+        }
+
+        if self.bool("nullable") && !s.types.is_empty() {
+            s.types.add(Type::Null);
+        }
+
+        // readOnly and writeOnly properties are required only in
+        // responses and requests respectively
+        if let Some(Value::Object(props)) = self.value("properties") {
+            s.required.retain(|pname| {
+                let Some(Value::Object(obj)) = props.get(pname) else {
+                    return true;
+                };
+                !matches!(obj.get("readOnly"), Some(Value::Bool(true)))
+                    && !matches!(obj.get("writeOnly"), Some(Value::Bool(true)))
+            });
+        }
+
+        if let Some(Value::Object(obj)) = self.value("discriminator") {
+            if let Some(Value::String(prop)) = obj.get("propertyName") {
+                let mut mapping = AHashMap::new();
+                if !s.one_of.is_empty() || !s.any_of.is_empty() {
+                    // implicit mapping by schema name
+                    for pname in ["oneOf", "anyOf"] {
+                        let Some(Value::Array(arr)) = self.value(pname) else {
+                            continue;
+                        };
+                        for item in arr {
+                            let Some(Value::String(ref_)) = item.get("$ref") else {
+                                continue;
+                            };
+                            let name = ref_.rsplit('/').next().unwrap_or(ref_);
+                            if let Some(sch) = self.enqueue_url(ref_)? {
+                                mapping.insert(name.to_owned(), sch);
+                            }
+                        }
+                    }
+                    if let Some(Value::Object(obj)) = obj.get("mapping") {
+                        for (value, ref_) in obj {
+                            let Value::String(ref_) = ref_ else {
+                                continue;
+                            };
+                            if ref_.contains(['#', '/', '.']) {
+                                if let Some(sch) = self.enqueue_url(ref_)? {
+                                    mapping.insert(value.clone(), sch);
+                                }
+                                continue;
+                            }
+                            // schema name, in components of the document
+                            let ptr = JsonPointer::from("/components/schemas").append(ref_);
+                            if self.root.doc.pointer(&ptr.0).is_none() {
+                                return Err(CompileError::InvalidOpenApi {
+                                    loc: self.up.format("discriminator"),
+                                    reason: format!("mapping to missing schema {ref_:?}"),
+                                });
+                            }
+                            let sch = self.enqueue_schema(ptr);
+                            mapping.insert(value.clone(), sch);
+                        }
+                    }
+                }
+                s.discriminator = Some(Discriminator {
+                    prop: prop.clone(),
+                    mapping,
+                });
+            }
+        }
+
+        if let (None, Some(Value::String(format))) = (&s.format, self.value("format")) {
+            s.format = OPENAPI_FORMATS.get(format.as_str()).cloned();
+        }
+
+        Ok(())
+    }
+
+    fn compile_kubernetes(&mut self, s: &mut Schema) -> Result<(), CompileError> {
+        if self.bool("x-kubernetes-int-or-string") && s.types.is_empty() {
+            s.types.add(Type::Integer);
+            s.types.add(Type::String);
+        }
+
+        if s.types.contains(Type::Object) {
+            // schema being compiled describes a kubernetes object
+            let resource =
+                self.up.ptr.is_empty() || !self.root.draft.is_subschema(self.up.ptr.as_str());
+            if resource || self.bool("x-kubernetes-embedded-resource") {
+                s.embedded_resource = true;
+                for pname in ["apiVersion", "kind"] {
+                    if !s.required.iter().any(|p| p == pname) {
+                        s.required.push(pname.to_owned());
+                    }
+                }
+            }
+            if s.additional_properties.is_none()
+                && !self.bool("x-kubernetes-preserve-unknown-fields")
+            {
+                s.additional_properties = Some(Additional::Bool(false));
+            }
+        }
+
+        match self.value("x-kubernetes-list-type") {
+            Some(Value::String(t)) if t == "set" => s.unique_items = true,
+            Some(Value::String(t)) if t == "map" => {
+                if let Some(keys) = self.value("x-kubernetes-list-map-keys") {
+                    s.list_map_keys = to_strings(keys);
+                }
             }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn compile_error_message(&mut self, s: &mut Schema) -> Result<(), CompileError> {
+        fn to_map(obj: &Map<String, Value>) -> AHashMap<String, String> {
+            obj.iter()
+                .filter_map(|(k, v)| v.as_str().map(|v| (k.clone(), v.to_owned())))
+                .collect()
         }
 
+        s.error_message = match self.value("errorMessage") {
+            Some(Value::String(msg)) => Some(ErrorMessage {
+                all: Some(msg.clone()),
+                ..Default::default()
+            }),
+            Some(Value::Object(obj)) => {
+                let mut em = ErrorMessage::default();
+                for (kw, msg) in obj {
+                    match (kw.as_str(), msg) {
+                        (_, Value::String(msg)) => {
+                            em.keywords.insert(kw.clone(), msg.clone());
+                        }
+                        ("required", Value::Object(obj)) => em.required = to_map(obj),
+                        ("properties", Value::Object(obj)) => em.properties = to_map(obj),
+                        _ => {}
+                    }
+                }
+                Some(em)
+            }
+            _ => None,
+        };
         Ok(())
     }
 
@@ -561,7 +1130,7 @@ impl<'c, 'v, 'l, 's, 'r, 'q> ObjCompiler<'c, 'v, 'l, 's, 'r, 'q> {
     }
 
     fn compile_draft2019(&mut self, s: &mut Schema) -> Result<(), CompileError> {
-        if self.has_vocab("core") {
+        if self.has_vocab("core") && self.draft_version() < VERSION_NEXT {
             s.recursive_ref = self.enqueue_ref("$recursiveRef")?;
             s.recursive_anchor = self.bool("$recursiveAnchor");
         }
@@ -631,6 +1200,28 @@ impl<'c, 'v, 'l, 's, 'r, 'q> ObjCompiler<'c, 'v, 'l, 's, 'r, 'q> {
 
         Ok(())
     }
+
+    fn compile_draft_next(&mut self, s: &mut Schema) -> Result<(), CompileError> {
+        if self.has_vocab("applicator") {
+            if let Some(Value::Object(deps)) = self.value("propertyDependencies") {
+                for (pname, pvalue) in deps {
+                    let Value::Object(obj) = pvalue else {
+                        continue;
+                    };
+                    let schs = obj
+                        .keys()
+                        .map(|k| {
+                            let ptr = self.up.ptr.append2("propertyDependencies", pname);
+                            (k.clone(), self.enqueue_schema(ptr.append(k)))
+                        })
+                        .collect();
+                    s.property_dependencies.push((pname.clone(), schs));
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
 // enqueue helpers
@@ -686,6 +1277,10 @@ impl<'c, 'v, 'l, 's, 'r, 'q> ObjCompiler<'c, 'v, 'l, 's, 'r, 'q> {
         let Some(Value::String(ref_)) = self.obj.get(pname) else {
             return Ok(None);
         };
+        self.enqueue_url(ref_)
+    }
+
+    fn enqueue_url(&mut self, ref_: &str) -> Result<Option<SchemaIndex>, CompileError> {
         let base_url = self.root.base_url(&self.up.ptr);
         let abs_ref = UrlFrag::join(base_url, ref_)?;
         if let Some(resolved_ref) = self.root.resolve(&abs_ref)? {
@@ -697,6 +1292,45 @@ impl<'c, 'v, 'l, 's, 'r, 'q> ObjCompiler<'c, 'v, 'l, 's, 'r, 'q> {
         Ok(Some(self.queue.enqueue_schema(self.schemas, up)))
     }
 
+    // draft3 `type` and `disallow` can contain schemas along with type names
+    fn enqueue_types(&mut self, pname: &'static str) -> (Types, Vec<SchemaIndex>) {
+        let mut types = Types::default();
+        let mut schemas = vec![];
+        let mut add_type = |t: &str| {
+            if t == "any" {
+                for t in [
+                    Type::Null,
+                    Type::Boolean,
+                    Type::Number,
+                    Type::String,
+                    Type::Array,
+                    Type::Object,
+                ] {
+                    types.add(t);
+                }
+            } else if let Some(t) = Type::from_str(t) {
+                types.add(t);
+            }
+        };
+        match self.value(pname) {
+            Some(Value::String(t)) => add_type(t),
+            Some(Value::Array(arr)) => {
+                for (i, item) in arr.iter().enumerate() {
+                    match item {
+                        Value::String(t) => add_type(t),
+                        Value::Object(_) => {
+                            let ptr = self.up.ptr.append2(pname, &i.to_string());
+                            schemas.push(self.enqueue_schema(ptr));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+        (types, schemas)
+    }
+
     fn enquue_additional(&mut self, pname: &'static str) -> Option<Additional> {
         if let Some(Value::Bool(b)) = self.obj.get(pname) {
             Some(Additional::Bool(*b))
@@ -751,10 +1385,34 @@ impl<'c, 'v, 'l, 's, 'r, 'q> ObjCompiler<'c, 'v, 'l, 's, 'r, 'q> {
         }
         false
     }
+
+    fn regex(&mut self, pname: &str, pattern: &str) -> Result<ecma::Regex, CompileError> {
+        let limits = &self.c.regex_limits;
+        let count = self.queue.regexes.entry(self.up.url.clone()).or_default();
+        let regex = if self.root.regexes + *count >= limits.max_regexes {
+            Err(format!("number of regexes exceeds limit {}", limits.max_regexes).into())
+        } else if pattern.len() > limits.max_pattern_len {
+            let len = pattern.len();
+            Err(format!(
+                "pattern length {len} exceeds limit {}",
+                limits.max_pattern_len
+            )
+            .into())
+        } else {
+            self.c.regex_engine().compile(pattern, limits)
+        };
+        *count += 1;
+        regex.map_err(|src| CompileError::InvalidRegex {
+            url: self.up.format(pname),
+            regex: pattern.to_owned(),
+            src,
+        })
+    }
 }
 
 /// Error type for compilation failures.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum CompileError {
     /// Error in parsing `url`.
     ParseUrlError { url: String, src: Box<dyn Error> },
@@ -821,11 +1479,41 @@ pub enum CompileError {
         src: Box<dyn Error>,
     },
 
+    /// OpenAPI document at `loc` is not supported: `reason`.
+    InvalidOpenApi { loc: String, reason: String },
+
+    /// Loading `url` is not allowed by [`LoadPolicy`]: `reason`.
+    UrlNotAllowed { url: String, reason: String },
+
     /// Encountered bug in compiler implementation. Please report
     /// this as an issue for this crate.
     Bug(Box<dyn Error>),
 }
 
+impl CompileError {
+    /**
+    Returns location of the schema value which caused this error,
+    as url with json-pointer fragment.
+
+    Use [`Compiler::position`] to get its line and column.
+    */
+    pub fn location(&self) -> Option<String> {
+        match self {
+            Self::ValidationError { url, .. } => Some(url.clone()),
+            Self::ParseIdError { loc } | Self::ParseAnchorError { loc } => Some(loc.clone()),
+            Self::DuplicateId { url, ptr1, .. } => {
+                Some(format!("{url}#{}", Fragment::encode(ptr1)))
+            }
+            Self::DuplicateAnchor { url, ptr2, .. } => {
+                Some(format!("{url}#{}", Fragment::encode(ptr2)))
+            }
+            Self::InvalidRegex { url, .. } => Some(url.clone()),
+            Self::InvalidOpenApi { loc, .. } => Some(loc.clone()),
+            _ => None,
+        }
+    }
+}
+
 impl Error for CompileError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
@@ -912,6 +1600,12 @@ impl Display for CompileError {
                     write!(f, "invalid regex {} at {url}", quote(regex))
                 }
             }
+            Self::InvalidOpenApi { loc, reason } => {
+                write!(f, "invalid openapi document {loc}: {reason}")
+            }
+            Self::UrlNotAllowed { url, reason } => {
+                write!(f, "loading {url} is not allowed: {reason}")
+            }
             Self::Bug(src) => {
                 write!(
                     f,
@@ -943,6 +1637,7 @@ fn to_strings(v: &Value) -> Vec<String> {
 pub(crate) struct Queue {
     pub(crate) schemas: Vec<UrlPtr>,
     pub(crate) roots: HashMap<Url, Root>,
+    regexes: HashMap<Url, usize>, // number of regexes compiled, per document
 }
 
 impl Queue {
@@ -950,6 +1645,7 @@ impl Queue {
         Self {
             schemas: vec![],
             roots: HashMap::new(),
+            regexes: HashMap::new(),
         }
     }
 
@@ -977,11 +1673,11 @@ impl Queue {
         }
         if let Some(qindex) = self.schemas.iter().position(|e| *e == up) {
             // already queued for compilation
-            return SchemaIndex(schemas.size() + qindex);
+            return schemas.next_index(qindex);
         }
 
         // new compilation request
         self.schemas.push(up);
-        SchemaIndex(schemas.size() + self.schemas.len() - 1)
+        schemas.next_index(self.schemas.len() - 1)
     }
 }
@@ -8,11 +8,39 @@ use once_cell::sync::Lazy;
 use serde_json::{Map, Value};
 use url::Url;
 
-use crate::{compiler::*, root::Resource, util::*, SchemaIndex, Schemas};
+use crate::{compiler::*, root::Resource, util::*, SchemaIndex, Schemas, ValidationError};
 
-const POS_SELF: u8 = 1 << 0;
-const POS_PROP: u8 = 1 << 1;
-const POS_ITEM: u8 = 1 << 2;
+pub(crate) const POS_SELF: u8 = 1 << 0;
+pub(crate) const POS_PROP: u8 = 1 << 1;
+pub(crate) const POS_ITEM: u8 = 1 << 2;
+pub(crate) const POS_PROP_PROP: u8 = 1 << 3; // map of maps of subschemas
+
+// version used for draft-next, which is greater than any released draft
+pub(crate) const VERSION_NEXT: usize = 9999;
+
+pub(crate) static DRAFT3: Lazy<Draft> = Lazy::new(|| Draft {
+    version: 3,
+    id: "id",
+    url: "http://json-schema.org/draft-03/schema",
+    subschemas: HashMap::from([
+        // type agnostic
+        ("extends", POS_SELF | POS_ITEM),
+        ("type", POS_ITEM),
+        ("disallow", POS_ITEM),
+        // object
+        ("properties", POS_PROP),
+        ("additionalProperties", POS_SELF),
+        ("patternProperties", POS_PROP),
+        // array
+        ("items", POS_SELF | POS_ITEM),
+        ("additionalItems", POS_SELF),
+        ("dependencies", POS_PROP),
+    ]),
+    vocab_prefix: "",
+    all_vocabs: vec![],
+    default_vocabs: vec![],
+    dialect: Dialect::JsonSchema,
+});
 
 pub(crate) static DRAFT4: Lazy<Draft> = Lazy::new(|| Draft {
     version: 4,
@@ -37,6 +65,7 @@ pub(crate) static DRAFT4: Lazy<Draft> = Lazy::new(|| Draft {
     vocab_prefix: "",
     all_vocabs: vec![],
     default_vocabs: vec![],
+    dialect: Dialect::JsonSchema,
 });
 
 pub(crate) static DRAFT6: Lazy<Draft> = Lazy::new(|| {
@@ -50,6 +79,7 @@ pub(crate) static DRAFT6: Lazy<Draft> = Lazy::new(|| {
         vocab_prefix: "",
         all_vocabs: vec![],
         default_vocabs: vec![],
+        dialect: Dialect::JsonSchema,
     }
 });
 
@@ -64,6 +94,7 @@ pub(crate) static DRAFT7: Lazy<Draft> = Lazy::new(|| {
         vocab_prefix: "",
         all_vocabs: vec![],
         default_vocabs: vec![],
+        dialect: Dialect::JsonSchema,
     }
 });
 
@@ -91,6 +122,7 @@ pub(crate) static DRAFT2019: Lazy<Draft> = Lazy::new(|| {
             "content",
         ],
         default_vocabs: vec!["core", "applicator", "validation"],
+        dialect: Dialect::JsonSchema,
     }
 });
 
@@ -114,9 +146,45 @@ pub(crate) static DRAFT2020: Lazy<Draft> = Lazy::new(|| {
             "content",
         ],
         default_vocabs: vec!["core", "applicator", "unevaluated", "validation"],
+        dialect: Dialect::JsonSchema,
+    }
+});
+
+pub(crate) static DRAFT_NEXT: Lazy<Draft> = Lazy::new(|| {
+    let mut subschemas = DRAFT2020.subschemas.clone();
+    subschemas.extend([("propertyDependencies", POS_PROP_PROP)]);
+    Draft {
+        version: VERSION_NEXT,
+        id: "$id",
+        url: "https://json-schema.org/draft/next/schema",
+        subschemas,
+        vocab_prefix: "https://json-schema.org/draft/next/vocab/",
+        all_vocabs: DRAFT2020.all_vocabs.clone(),
+        default_vocabs: DRAFT2020.default_vocabs.clone(),
+        dialect: Dialect::JsonSchema,
     }
 });
 
+// openapi 3.0 schema objects are an extended subset of draft-04.
+// they are validated against draft-04 metaschema.
+pub(crate) static OPENAPI30: Lazy<Draft> = Lazy::new(|| Draft {
+    version: 4,
+    id: "id",
+    url: DRAFT4.url,
+    subschemas: DRAFT4.subschemas.clone(),
+    vocab_prefix: "",
+    all_vocabs: vec![],
+    default_vocabs: vec![],
+    dialect: Dialect::OpenApi30,
+});
+
+// kubernetes structural schemas are openapi 3.0 schema objects
+// with `x-kubernetes-*` extensions.
+pub(crate) static KUBERNETES: Lazy<Draft> = Lazy::new(|| Draft {
+    dialect: Dialect::Kubernetes,
+    ..OPENAPI30.clone()
+});
+
 pub(crate) static STD_METASCHEMAS: Lazy<Schemas> =
     Lazy::new(|| load_std_metaschemas().expect("std metaschemas must be compilable"));
 
@@ -126,14 +194,23 @@ pub(crate) fn latest() -> &'static Draft {
 
 // --
 
+#[derive(Clone)]
 pub(crate) struct Draft {
     pub(crate) version: usize,
     pub(crate) url: &'static str,
-    id: &'static str,                         // property name used to represent id
-    subschemas: HashMap<&'static str, u8>,    // location of subschemas
-    pub(crate) vocab_prefix: &'static str,    // prefix used for vocabulary
+    pub(crate) id: &'static str, // property name used to represent id
+    pub(crate) subschemas: HashMap<&'static str, u8>, // location of subschemas
+    pub(crate) vocab_prefix: &'static str, // prefix used for vocabulary
     pub(crate) all_vocabs: Vec<&'static str>, // names of supported vocabs
     pub(crate) default_vocabs: Vec<&'static str>, // names of default vocabs
+    pub(crate) dialect: Dialect, // keywords understood on top of draft
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Dialect {
+    JsonSchema,
+    OpenApi30,
+    Kubernetes,
 }
 
 impl Draft {
@@ -150,22 +227,26 @@ impl Draft {
         }
         match url {
             "json-schema.org/schema" => Some(latest()),
+            "json-schema.org/draft/next/schema" => Some(&DRAFT_NEXT),
             "json-schema.org/draft/2020-12/schema" => Some(&DRAFT2020),
             "json-schema.org/draft/2019-09/schema" => Some(&DRAFT2019),
             "json-schema.org/draft-07/schema" => Some(&DRAFT7),
             "json-schema.org/draft-06/schema" => Some(&DRAFT6),
             "json-schema.org/draft-04/schema" => Some(&DRAFT4),
+            "json-schema.org/draft-03/schema" => Some(&DRAFT3),
             _ => None,
         }
     }
 
     fn get_schema(&self) -> Option<SchemaIndex> {
         let url = match self.version {
+            VERSION_NEXT => "https://json-schema.org/draft/next/schema",
             2020 => "https://json-schema.org/draft/2020-12/schema",
             2019 => "https://json-schema.org/draft/2019-09/schema",
             7 => "http://json-schema.org/draft-07/schema",
             6 => "http://json-schema.org/draft-06/schema",
             4 => "http://json-schema.org/draft-04/schema",
+            3 => "http://json-schema.org/draft-03/schema",
             _ => return None,
         };
         let up = UrlPtr {
@@ -176,17 +257,26 @@ impl Draft {
     }
 
     pub(crate) fn validate(&self, up: &UrlPtr, v: &Value) -> Result<(), CompileError> {
+        self.validate_value(v)?
+            .map_err(|src| CompileError::ValidationError {
+                url: up.to_string(),
+                src,
+            })
+    }
+
+    // validates `v` against metaschema of this draft
+    pub(crate) fn validate_value(
+        &self,
+        v: &Value,
+    ) -> Result<Result<(), ValidationError<'static, 'static>>, CompileError> {
         let Some(sch) = self.get_schema() else {
             return Err(CompileError::Bug(
                 format!("no metaschema preloaded for draft {}", self.version).into(),
             ));
         };
-        STD_METASCHEMAS
+        Ok(STD_METASCHEMAS
             .validate(v, sch)
-            .map_err(|src| CompileError::ValidationError {
-                url: up.to_string(),
-                src: src.clone_static(),
-            })
+            .map_err(|src| src.clone_static()))
     }
 
     fn get_id<'a>(&self, obj: &'a Map<String, Value>) -> Option<&'a str> {
@@ -346,6 +436,18 @@ impl Draft {
                     }
                 }
             }
+            if pos & POS_PROP_PROP != 0 {
+                if let Value::Object(obj) = v {
+                    for (pname, pvalue) in obj {
+                        if let Value::Object(obj) = pvalue {
+                            for (name, sch) in obj {
+                                let ptr = sch_ptr.append2(kw, pname).append(name);
+                                self.collect_resources(sch, base, ptr, url, resources)?;
+                            }
+                        }
+                    }
+                }
+            }
         }
         Ok(())
     }
@@ -383,6 +485,15 @@ impl Draft {
                         return true;
                     }
                 }
+                if pos & POS_PROP_PROP != 0 {
+                    let (_, ptr) = split(ptr);
+                    if !ptr.is_empty() {
+                        let (_, ptr) = split(ptr);
+                        if self.is_subschema(ptr) {
+                            return true;
+                        }
+                    }
+                }
             }
         }
 
@@ -394,11 +505,13 @@ fn load_std_metaschemas() -> Result<Schemas, CompileError> {
     let mut schemas = Schemas::new();
     let mut compiler = Compiler::new();
     compiler.enable_format_assertions();
+    compiler.compile("https://json-schema.org/draft/next/schema", &mut schemas)?;
     compiler.compile("https://json-schema.org/draft/2020-12/schema", &mut schemas)?;
     compiler.compile("https://json-schema.org/draft/2019-09/schema", &mut schemas)?;
     compiler.compile("http://json-schema.org/draft-07/schema", &mut schemas)?;
     compiler.compile("http://json-schema.org/draft-06/schema", &mut schemas)?;
     compiler.compile("http://json-schema.org/draft-04/schema", &mut schemas)?;
+    compiler.compile("http://json-schema.org/draft-03/schema", &mut schemas)?;
     Ok(schemas)
 }
 
@@ -425,6 +538,11 @@ mod tests {
             ("https://json-schema.org/draft/2020-12/schema", Some(2020)), // https url
             ("https://json-schema.org/schema", Some(latest().version)),  // latest
             ("https://json-schema.org/draft-04/schema", Some(4)),
+            ("http://json-schema.org/draft-03/schema#", Some(3)),
+            (
+                "https://json-schema.org/draft/next/schema",
+                Some(VERSION_NEXT),
+            ),
         ];
         for (url, version) in tests {
             let got = Draft::from_url(url).map(|d| d.version);
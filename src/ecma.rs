@@ -1,8 +1,283 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, sync::Arc};
+
+use once_cell::sync::Lazy;
 
 use regex_syntax::ast::parse::Parser;
 use regex_syntax::ast::{self, *};
 
+/**
+Limits on regexes of `pattern` and `patternProperties`, to bound resources
+used in compiling untrusted schemas. See [`Compiler::set_regex_limits`].
+
+```
+# use boon::*;
+let mut compiler = Compiler::new();
+compiler.set_regex_limits(RegexLimits {
+    max_pattern_len: 1000,
+    max_regexes: 100,
+    ..Default::default()
+});
+```
+
+[`Compiler::set_regex_limits`]: crate::Compiler::set_regex_limits
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegexLimits {
+    /// Maximum length of pattern in bytes.
+    pub max_pattern_len: usize,
+    /// Maximum size of compiled regex in bytes.
+    pub size_limit: usize,
+    /// Maximum size of cache used by lazy DFA in matching, in bytes.
+    pub dfa_size_limit: usize,
+    /// Maximum number of regexes in schemas compiled from single document.
+    pub max_regexes: usize,
+    /// Maximum number of backtracking steps in matching with
+    /// [`FancyRegexEngine`]. Exceeding it aborts validation with
+    /// [`Limit::BacktrackLimit`].
+    ///
+    /// [`FancyRegexEngine`]: crate::FancyRegexEngine
+    /// [`Limit::BacktrackLimit`]: crate::Limit::BacktrackLimit
+    pub backtrack_limit: usize,
+}
+
+impl Default for RegexLimits {
+    /// No limits on pattern length and number of regexes. Other limits
+    /// are defaults of `regex` crate.
+    fn default() -> Self {
+        Self {
+            max_pattern_len: usize::MAX,
+            size_limit: 10 * (1 << 20),
+            dfa_size_limit: 2 * (1 << 20),
+            max_regexes: usize::MAX,
+            backtrack_limit: 1_000_000,
+        }
+    }
+}
+
+/**
+Engine compiling ECMA-262 regexes of `pattern`, `patternProperties` and
+format `regex`. See [`Compiler::set_regex_engine`].
+
+Built-in engines are [`StdRegexEngine`], and [`FancyRegexEngine`] with
+feature `fancy-regex`. The latter is used by default if the feature is
+enabled, otherwise the former.
+
+[`Compiler::set_regex_engine`]: crate::Compiler::set_regex_engine
+*/
+pub trait RegexEngine: Send + Sync {
+    /**
+    Compiles ECMA-262 `pattern`.
+
+    Engines should honor those of `limits` that apply to them.
+    [`RegexLimits::max_pattern_len`] and [`RegexLimits::max_regexes`]
+    are checked by the compiler.
+    */
+    fn compile(
+        &self,
+        pattern: &str,
+        limits: &RegexLimits,
+    ) -> Result<Box<dyn CompiledRegex>, Box<dyn std::error::Error>>;
+
+    /**
+    Checks that `pattern` is valid ECMA-262 regex, for format `regex`.
+
+    Default implementation compiles `pattern` with default limits.
+    */
+    fn validate(&self, pattern: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.compile(pattern, &RegexLimits::default()).map(|_| ())
+    }
+}
+
+/// Regex compiled by [`RegexEngine`].
+pub trait CompiledRegex: Send + Sync {
+    /**
+    Tells whether `s` matches.
+
+    Returns `None` if matching exceeds limits of the engine, in which
+    case validation is aborted with [`Limit::BacktrackLimit`].
+
+    [`Limit::BacktrackLimit`]: crate::Limit::BacktrackLimit
+    */
+    fn is_match(&self, s: &str) -> Option<bool>;
+
+    /// Returns the pattern, as reported in [`ErrorKind::Pattern`].
+    ///
+    /// [`ErrorKind::Pattern`]: crate::ErrorKind::Pattern
+    fn as_str(&self) -> &str;
+}
+
+pub(crate) type Regex = Box<dyn CompiledRegex>;
+
+pub(crate) static DEFAULT_ENGINE: Lazy<Arc<dyn RegexEngine>> = Lazy::new(|| {
+    #[cfg(feature = "fancy-regex")]
+    return Arc::new(FancyRegexEngine);
+    #[cfg(not(feature = "fancy-regex"))]
+    return Arc::new(StdRegexEngine);
+});
+
+/**
+[`RegexEngine`] using `regex` crate, after converting patterns into its syntax.
+
+It runs in linear time, but does not support lookaround and backreferences.
+*/
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StdRegexEngine;
+
+impl StdRegexEngine {
+    fn build(pattern: &str, limits: &RegexLimits) -> Result<regex::Regex, regex::Error> {
+        regex::RegexBuilder::new(pattern)
+            .size_limit(limits.size_limit)
+            .dfa_size_limit(limits.dfa_size_limit)
+            .build()
+    }
+}
+
+impl RegexEngine for StdRegexEngine {
+    fn compile(
+        &self,
+        pattern: &str,
+        limits: &RegexLimits,
+    ) -> Result<Box<dyn CompiledRegex>, Box<dyn std::error::Error>> {
+        let regex = Self::build(convert(pattern)?.as_ref(), limits)?;
+        Ok(Box::new(StdRegex(regex)))
+    }
+
+    fn validate(&self, pattern: &str) -> Result<(), Box<dyn std::error::Error>> {
+        convert(pattern).map(|_| ())
+    }
+}
+
+struct StdRegex(regex::Regex);
+
+impl CompiledRegex for StdRegex {
+    fn is_match(&self, s: &str) -> Option<bool> {
+        Some(self.0.is_match(s))
+    }
+
+    fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+/**
+[`RegexEngine`] matching patterns using lookaround or backreferences with
+backtracking engine of `fancy-regex` crate, after translating their ECMA-262
+escapes. Other patterns are matched as in [`StdRegexEngine`].
+
+Matching is bounded by [`RegexLimits::backtrack_limit`].
+*/
+#[cfg(feature = "fancy-regex")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FancyRegexEngine;
+
+#[cfg(feature = "fancy-regex")]
+impl RegexEngine for FancyRegexEngine {
+    fn compile(
+        &self,
+        pattern: &str,
+        limits: &RegexLimits,
+    ) -> Result<Box<dyn CompiledRegex>, Box<dyn std::error::Error>> {
+        match convert(pattern) {
+            Ok(converted) => {
+                let regex = StdRegexEngine::build(converted.as_ref(), limits)?;
+                Ok(Box::new(StdRegex(regex)))
+            }
+            Err(e) if needs_backtracking(e.as_ref()) => {
+                let regex = fancy_regex::RegexBuilder::new(&translate(pattern)?)
+                    .backtrack_limit(limits.backtrack_limit)
+                    .delegate_size_limit(limits.size_limit)
+                    .delegate_dfa_size_limit(limits.dfa_size_limit)
+                    .build()?;
+                Ok(Box::new(FancyRegex(regex)))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn validate(&self, pattern: &str) -> Result<(), Box<dyn std::error::Error>> {
+        match convert(pattern) {
+            Ok(_) => Ok(()),
+            Err(e) if needs_backtracking(e.as_ref()) => {
+                fancy_regex::Expr::parse_tree(&translate(pattern)?)?;
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(feature = "fancy-regex")]
+struct FancyRegex(fancy_regex::Regex);
+
+#[cfg(feature = "fancy-regex")]
+impl CompiledRegex for FancyRegex {
+    fn is_match(&self, s: &str) -> Option<bool> {
+        self.0.is_match(s).ok()
+    }
+
+    fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+// checks `pattern` is valid ecma regex, without compiling it
+pub(crate) fn validate(pattern: &str) -> Result<(), Box<dyn std::error::Error>> {
+    DEFAULT_ENGINE.validate(pattern)
+}
+
+// tells whether `e` is because `regex` crate does not support lookaround or backreferences
+#[cfg(feature = "fancy-regex")]
+fn needs_backtracking(e: &(dyn std::error::Error + 'static)) -> bool {
+    matches!(
+        e.downcast_ref::<Error>().map(Error::kind),
+        Some(ErrorKind::UnsupportedLookAround | ErrorKind::UnsupportedBackreference)
+    )
+}
+
+// same as `convert`, but without parsing `pattern`, which regex_syntax cannot
+// parse if it uses lookaround or backreferences. Escapes are translated as
+// in `fix_error` and `Translator`.
+#[cfg(feature = "fancy-regex")]
+fn translate(pattern: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let mut out = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        let Some(c) = chars.next() else {
+            out.push('\\');
+            break;
+        };
+        match c {
+            'd' => out.push_str(DIGIT),
+            'D' => out.push_str(NOT_DIGIT),
+            'w' => out.push_str(WORD),
+            'W' => out.push_str(NOT_WORD),
+            's' => out.push_str(SPACE),
+            'S' => out.push_str(NOT_SPACE),
+            'a' => Err("\\a is not an ECMA 262 control escape")?,
+            'c' => match chars.next_if(char::is_ascii_alphabetic) {
+                Some(control_letter) => out.push(((control_letter as u8) % 32) as char),
+                None => out.push_str(r"\c"),
+            },
+            _ => {
+                out.push('\\');
+                out.push(c);
+            }
+        }
+    }
+    Ok(out)
+}
+
+const DIGIT: &str = "[0-9]";
+const NOT_DIGIT: &str = "[^0-9]";
+const WORD: &str = "[A-Za-z0-9_]";
+const NOT_WORD: &str = "[^A-Za-z0-9_]";
+const SPACE: &str = "[ \t\n\r\u{000b}\u{000c}\u{00a0}\u{feff}\u{2003}\u{2029}]";
+const NOT_SPACE: &str = "[^ \t\n\r\u{000b}\u{000c}\u{00a0}\u{feff}\u{2003}\u{2029}]";
+
 // covert ecma regex to rust regex if possible
 // see https://262.ecma-international.org/11.0/#sec-regexp-regular-expression-objects
 pub(crate) fn convert(pattern: &str) -> Result<Cow<str>, Box<dyn std::error::Error>> {
@@ -92,23 +367,13 @@ impl<'a> Translator<'a> {
     fn replace_class_class(&mut self, perl: &ClassPerl) {
         match perl.kind {
             ClassPerlKind::Digit => {
-                self.replace(&perl.span, if perl.negated { "[^0-9]" } else { "[0-9]" });
+                self.replace(&perl.span, if perl.negated { NOT_DIGIT } else { DIGIT });
             }
             ClassPerlKind::Word => {
-                let with = &if perl.negated {
-                    "[^A-Za-z0-9_]"
-                } else {
-                    "[A-Za-z0-9_]"
-                };
-                self.replace(&perl.span, with);
+                self.replace(&perl.span, if perl.negated { NOT_WORD } else { WORD });
             }
             ClassPerlKind::Space => {
-                let with = &if perl.negated {
-                    "[^ \t\n\r\u{000b}\u{000c}\u{00a0}\u{feff}\u{2003}\u{2029}]"
-                } else {
-                    "[ \t\n\r\u{000b}\u{000c}\u{00a0}\u{feff}\u{2003}\u{2029}]"
-                };
-                self.replace(&perl.span, with);
+                self.replace(&perl.span, if perl.negated { NOT_SPACE } else { SPACE });
             }
         }
     }
@@ -181,6 +446,53 @@ mod tests {
         }
     }
 
+    #[test]
+    #[cfg(feature = "fancy-regex")]
+    fn test_backtracking() {
+        let limits = RegexLimits::default();
+        assert!(StdRegexEngine.compile(r"^(?!foo)\w+$", &limits).is_err());
+        let regex = FancyRegexEngine.compile(r"^(?!foo)\w+$", &limits).unwrap();
+        assert_eq!(regex.is_match("bar"), Some(true));
+        assert_eq!(regex.is_match("foobar"), Some(false));
+
+        let regex = FancyRegexEngine.compile(r"^(a)\1$", &limits).unwrap();
+        assert_eq!(regex.is_match("aa"), Some(true));
+        assert_eq!(regex.is_match("ab"), Some(false));
+
+        assert!(validate(r"(?<=a)b").is_ok());
+        assert!(validate(r"abc\adef").is_err());
+        assert!(validate(r"(?=a)bc\adef").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "fancy-regex")]
+    fn test_backtracking_ecma_compat() {
+        // both engines must agree, irrespective of lookaround
+        let limits = RegexLimits::default();
+        let tests = [
+            (r"^\d+$", ["12", "\u{661}\u{662}", "1a"]),
+            (r"^\D+$", ["ab", "\u{661}", "1"]),
+            (r"^\w+$", ["ab_1", "\u{e9}", "a-b"]),
+            (r"^\W+$", ["-", "\u{e9}", "a"]),
+            (r"^\s+$", [" \t", "\u{2029}", "\u{2028}"]),
+            (r"^[\d\s]+$", ["1 2", "\u{661}", "x"]),
+            (r"^\cJ\\d$", ["\n\\d", "\n1", "cJ"]),
+        ];
+        for (pattern, inputs) in tests {
+            let std = StdRegexEngine.compile(pattern, &limits).unwrap();
+            let fancy = FancyRegexEngine
+                .compile(&format!("{pattern}(?!x)"), &limits)
+                .unwrap();
+            for input in inputs {
+                assert_eq!(
+                    std.is_match(input),
+                    fancy.is_match(input),
+                    "{pattern} {input:?}"
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_ecma_compat_invalid() {
         // println!("{:#?}", Parser::new().parse(r#"a\a"#));
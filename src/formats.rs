@@ -4,9 +4,10 @@ use std::{
     net::{Ipv4Addr, Ipv6Addr},
 };
 
+use base64::Engine;
 use once_cell::sync::Lazy;
 use percent_encoding::percent_decode_str;
-use serde_json::Value;
+use serde_json::{Number, Value};
 use url::Url;
 
 use crate::ecma;
@@ -47,11 +48,23 @@ pub(crate) static FORMATS: Lazy<HashMap<&'static str, Format>> = Lazy::new(|| {
     m
 });
 
+// formats defined by openapi 3.0. `double`, `binary` and
+// `password` are not listed, since they accept any value.
+pub(crate) static OPENAPI_FORMATS: Lazy<HashMap<&'static str, Format>> = Lazy::new(|| {
+    let mut m = HashMap::<&'static str, Format>::new();
+    let mut register = |name, func| m.insert(name, Format { name, func });
+    register("int32", validate_int32);
+    register("int64", validate_int64);
+    register("float", validate_float);
+    register("byte", validate_byte);
+    m
+});
+
 fn validate_regex(v: &Value) -> Result<(), Box<dyn Error>> {
     let Value::String(s) = v else {
         return Ok(());
     };
-    ecma::convert(s).map(|_| ())
+    ecma::validate(s)
 }
 
 fn validate_ipv4(v: &Value) -> Result<(), Box<dyn Error>> {
@@ -836,3 +849,57 @@ fn validate_uri_template(v: &Value) -> Result<(), Box<dyn Error>> {
     }
     Ok(())
 }
+
+fn validate_int32(v: &Value) -> Result<(), Box<dyn Error>> {
+    let Value::Number(n) = v else {
+        return Ok(());
+    };
+    check_int(n, i32::MIN.into(), i32::MAX.into())
+}
+
+fn validate_int64(v: &Value) -> Result<(), Box<dyn Error>> {
+    let Value::Number(n) = v else {
+        return Ok(());
+    };
+    check_int(n, i64::MIN, i64::MAX)
+}
+
+fn check_int(n: &Number, min: i64, max: i64) -> Result<(), Box<dyn Error>> {
+    let i = if let Some(i) = n.as_i64() {
+        i
+    } else if n.is_u64() {
+        Err("out of range")?
+    } else {
+        let f = n.as_f64().unwrap_or(f64::NAN);
+        if f.fract() != 0.0 {
+            Err("not an integer")?
+        }
+        if f < min as f64 || f > max as f64 {
+            Err("out of range")?
+        }
+        f as i64
+    };
+    if i < min || i > max {
+        Err("out of range")?
+    }
+    Ok(())
+}
+
+fn validate_float(v: &Value) -> Result<(), Box<dyn Error>> {
+    let Value::Number(n) = v else {
+        return Ok(());
+    };
+    let f = n.as_f64().unwrap_or(f64::INFINITY);
+    if f.abs() > f32::MAX.into() {
+        Err("out of range")?
+    }
+    Ok(())
+}
+
+fn validate_byte(v: &Value) -> Result<(), Box<dyn Error>> {
+    let Value::String(s) = v else {
+        return Ok(());
+    };
+    base64::engine::general_purpose::STANDARD.decode(s)?;
+    Ok(())
+}
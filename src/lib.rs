@@ -27,6 +27,21 @@ The use of this option is HIGHLY encouraged to ensure continued
 correct operation of your schema. The current default value will
 not stay the same over time.
 
+Schema files are parsed based on their extension, see [`SourceFormat`].
+Besides json and jsonc, features `yaml`, `json5` and `toml` enable
+parsing of `.yaml`/`.yml`, `.json5` and `.toml` files. A document in
+multi-document yaml file is selected by its index in query, like
+`schemas.yaml?document=1#/$defs/name`. Use [`Compiler::add_resource_text`]
+to add resources from text.
+
+Custom [`UrlLoader`]s can return raw [`UrlContent`] with its media type,
+so that documents from any source are parsed the same way.
+
+A [`Catalog`] in SchemaStore format selects schema of a file by its path.
+
+Request and response bodies can be validated against an OpenAPI
+document compiled with [`Compiler::compile_openapi`].
+
 # Examples
 
 - [example_from_strings]: loading schemas from Strings
@@ -80,6 +95,8 @@ for example:
 - [flag] `validation_error.flag_output()`
 - [basic] `validation_error.basic_output()`
 - [detailed] `validation_error.detailed_output()`
+- [list] `validation_error.list_output()`, from draft-next
+- [hierarchical] `validation_error.hierarchical_output()`, from draft-next
 
 The output object implements `serde::Serialize`.
 
@@ -90,6 +107,32 @@ println!("{output}"); // prints unformatted json
 println!("{output:#}"); // prints indented json
 ```
 
+# Source Positions
+
+[`SourceMap::parse_json`] parses an instance along with positions of
+its values. `validation_error.with_source_map(&source_map)` can be
+displayed and converted into output formats like `validation_error`,
+but reports the line and column of each instance location. With
+feature `yaml`, [`SourceMap::parse_yaml`] does the same for yaml.
+
+Positions of schema files are recorded when they are loaded, so that
+[`CompileError::location`] can be mapped to line and column using
+[`Compiler::position`].
+
+# Localized Messages
+
+Error messages can be translated or rephrased by implementing
+[`MessageCatalog`]. `validation_error.localize(&catalog)` can be
+displayed and converted into output formats like `validation_error`,
+but uses templates from `catalog`.
+
+Schema authors can replace messages using `errorMessage` keyword. Its
+value is either a string that replaces all errors of the subschema, or
+an object mapping keyword names to messages. The `required` and
+`properties` entries of the object can map property names to messages.
+Messages can refer to the parameters of [`ErrorKind`] as in
+[`MessageCatalog`], and take precedence over the catalog.
+
 [example_from_strings]: https://github.com/santhosh-tekuri/boon/blob/d466730e5e5c7c663bd6739e74e39d1e2f7baae4/tests/examples.rs#L22
 [example_from_https]: https://github.com/santhosh-tekuri/boon/blob/d466730e5e5c7c663bd6739e74e39d1e2f7baae4/tests/examples.rs#L62
 [example_from_yaml_files]: https://github.com/santhosh-tekuri/boon/blob/d466730e5e5c7c663bd6739e74e39d1e2f7baae4/tests/examples.rs#L86
@@ -99,48 +142,96 @@ println!("{output:#}"); // prints indented json
 [flag]: https://json-schema.org/draft/2020-12/json-schema-core.html#name-flag
 [basic]: https://json-schema.org/draft/2020-12/json-schema-core.html#name-basic
 [detailed]: https://json-schema.org/draft/2020-12/json-schema-core.html#name-detailed
+[list]: https://json-schema.org/draft/next/json-schema-core.html#name-list
+[hierarchical]: https://json-schema.org/draft/next/json-schema-core.html#name-hierarchical
 
 */
 
+mod cache;
+mod catalog;
 mod compiler;
 mod content;
 mod draft;
 mod ecma;
 mod formats;
 mod loader;
+mod messages;
+mod openapi;
 mod output;
 mod root;
 mod roots;
+mod source;
+mod upgrade;
 mod util;
 mod validator;
 
+#[allow(deprecated)]
+pub use output::LocalizedError;
 pub use {
+    cache::DocumentCache,
+    catalog::{Catalog, CatalogEntry},
     compiler::{CompileError, Compiler, Draft},
     content::{Decoder, MediaType},
+    ecma::{CompiledRegex, RegexEngine, RegexLimits, StdRegexEngine},
     formats::Format,
-    loader::UrlLoader,
+    loader::{LoadPolicy, UrlContent, UrlLoader},
+    messages::MessageCatalog,
+    openapi::{OpenApi, OpenApiError},
     output::{
-        AbsoluteKeywordLocation, FlagOutput, KeywordPath, OutputError, OutputUnit, SchemaToken,
+        AbsoluteKeywordLocation, ErrorReport, FlagOutput, KeywordPath, OutputError, OutputNode,
+        OutputUnit, SchemaToken,
     },
+    source::{ParseError, Position, SourceFormat, SourceMap},
+    upgrade::UpgradeError,
     validator::{InstanceLocation, InstanceToken},
 };
 
-use std::{borrow::Cow, collections::HashMap, error::Error, fmt::Display};
+#[cfg(not(target_arch = "wasm32"))]
+pub use loader::SandboxedFileLoader;
+
+#[cfg(feature = "fancy-regex")]
+pub use ecma::FancyRegexEngine;
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt::Display,
+    sync::{atomic::AtomicBool, Arc},
+    time::Instant,
+};
 
 use ahash::AHashMap;
-use regex::Regex;
+use ecma::Regex;
 use serde_json::{Number, Value};
+use url::Url;
 use util::*;
 
-/// Identifier to compiled schema.
+/**
+Identifier to compiled schema.
+
+Each index carries generation of its slot in [`Schemas`], so that index
+of a removed schema never refers to a schema compiled later in its place.
+*/
 #[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct SchemaIndex(usize);
+pub struct SchemaIndex {
+    slot: usize,
+    gen: u32,
+}
 
 /// Collection of compiled schemas.
 #[derive(Default)]
 pub struct Schemas {
-    list: Vec<Schema>,
-    map: HashMap<UrlPtr, usize>, // loc => schema-index
+    list: Vec<Slot>,
+    map: HashMap<UrlPtr, SchemaIndex>, // loc => schema-index
+    free: Vec<usize>,                  // empty slots, reused before growing list
+    roots: HashSet<SchemaIndex>,       // returned by Compiler::compile
+}
+
+#[derive(Default)]
+struct Slot {
+    gen: u32,
+    sch: Option<Schema>,
 }
 
 impl Schemas {
@@ -148,29 +239,178 @@ impl Schemas {
         Self::default()
     }
 
+    // returns index of `n`th schema to be inserted next
+    pub(crate) fn next_index(&self, n: usize) -> SchemaIndex {
+        match self.free.get(n) {
+            Some(&slot) => SchemaIndex {
+                slot,
+                gen: self.list[slot].gen,
+            },
+            None => SchemaIndex {
+                slot: self.list.len() + n - self.free.len(),
+                gen: 0,
+            },
+        }
+    }
+
     fn insert(&mut self, locs: Vec<UrlPtr>, compiled: Vec<Schema>) {
-        for (up, sch) in locs.into_iter().zip(compiled.into_iter()) {
-            let i = self.list.len();
-            self.list.push(sch);
-            self.map.insert(up, i);
+        let indexes: Vec<_> = (0..compiled.len()).map(|i| self.next_index(i)).collect();
+        let reused = compiled.len().min(self.free.len());
+        for ((up, sch), idx) in locs.into_iter().zip(compiled).zip(indexes) {
+            debug_assert_eq!(sch.idx, idx);
+            match self.list.get_mut(idx.slot) {
+                Some(slot) => slot.sch = Some(sch),
+                None => self.list.push(Slot {
+                    gen: idx.gen,
+                    sch: Some(sch),
+                }),
+            }
+            self.map.insert(up, idx);
         }
+        self.free.drain(..reused);
     }
 
     fn get(&self, idx: SchemaIndex) -> &Schema {
-        &self.list[idx.0] // todo: return bug
+        self.lookup(idx).expect("schema index must be valid") // todo: return bug
+    }
+
+    fn lookup(&self, idx: SchemaIndex) -> Option<&Schema> {
+        match self.list.get(idx.slot)? {
+            Slot {
+                gen,
+                sch: Some(sch),
+            } if *gen == idx.gen => Some(sch),
+            _ => None,
+        }
     }
 
     fn get_by_loc(&self, up: &UrlPtr) -> Option<&Schema> {
-        self.map.get(up).and_then(|&i| self.list.get(i))
+        self.map.get(up).and_then(|&idx| self.lookup(idx))
     }
 
-    /// Returns true if `sch_index` is generated for this instance.
+    pub(crate) fn add_root(&mut self, idx: SchemaIndex) {
+        self.roots.insert(idx);
+    }
+
+    // urls of documents, from which schemas are compiled
+    pub(crate) fn urls(&self) -> HashSet<&Url> {
+        self.map.keys().map(|up| &up.url).collect()
+    }
+
+    /**
+    Detaches schemas compiled from documents at `urls`, along with schemas
+    referencing them, so that their locations are compiled again.
+    Detached schemas remain valid until removed.
+
+    Returns detached roots and detached entries.
+    */
+    pub(crate) fn detach(
+        &mut self,
+        urls: &HashSet<Url>,
+    ) -> (Vec<SchemaIndex>, Vec<(UrlPtr, SchemaIndex)>) {
+        let mut referrers: HashMap<SchemaIndex, Vec<SchemaIndex>> = HashMap::new();
+        for &idx in self.map.values() {
+            for sub in self.get(idx).subschemas() {
+                referrers.entry(sub).or_default().push(idx);
+            }
+        }
+        let mut stale = HashSet::new();
+        let mut stack: Vec<SchemaIndex> = self
+            .map
+            .iter()
+            .filter(|(up, _)| urls.contains(&up.url))
+            .map(|(_, &idx)| idx)
+            .collect();
+        while let Some(idx) = stack.pop() {
+            if stale.insert(idx) {
+                stack.extend(referrers.get(&idx).into_iter().flatten());
+            }
+        }
+
+        let mut roots: Vec<SchemaIndex> = self.roots.intersection(&stale).copied().collect();
+        roots.sort();
+        let mut entries = vec![];
+        self.map.retain(|up, idx| {
+            if stale.contains(idx) {
+                entries.push((up.clone(), *idx));
+                return false;
+            }
+            true
+        });
+        (roots, entries)
+    }
+
+    /// Attaches entries detached by [`Schemas::detach`].
+    pub(crate) fn attach(&mut self, entries: Vec<(UrlPtr, SchemaIndex)>) {
+        self.map.extend(entries);
+    }
+
+    /// Returns true if `sch_index` is generated for this instance,
+    /// and is not removed.
     pub fn contains(&self, sch_index: SchemaIndex) -> bool {
-        self.list.get(sch_index.0).is_some()
+        self.lookup(sch_index).is_some()
     }
 
+    /// Returns number of compiled schemas, including subschemas.
     pub fn size(&self) -> usize {
-        self.list.len()
+        self.list.len() - self.free.len()
+    }
+
+    /**
+    Releases schema `sch_index` returned by [`Compiler::compile`], and
+    removes all schemas which are no longer reachable from other schemas
+    returned by [`Compiler::compile`].
+
+    Returns `false` if `sch_index` is not returned by [`Compiler::compile`]
+    for this instance, or is already released. Note that `sch_index` itself
+    is removed only if it is not reachable from other returned schemas,
+    for example if it is a subschema of one of them; otherwise it stays
+    contained until those are removed. Use [`Compiler::unload_unused`] to
+    release documents from which removed schemas were compiled.
+
+    ```
+    # use boon::*;
+    # use serde_json::json;
+    # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    compiler.add_resource("tenant1.json", json!({"type": "string"}))?;
+    let sch = compiler.compile("tenant1.json", &mut schemas)?;
+    assert!(schemas.remove(sch));
+    assert!(!schemas.contains(sch));
+    compiler.unload_unused(&schemas);
+    # Ok(())
+    # }
+    ```
+    */
+    pub fn remove(&mut self, sch_index: SchemaIndex) -> bool {
+        if !self.roots.remove(&sch_index) {
+            return false;
+        }
+
+        // mark schemas reachable from remaining roots
+        let mut reachable = HashSet::new();
+        let mut stack: Vec<SchemaIndex> = self.roots.iter().copied().collect();
+        while let Some(idx) = stack.pop() {
+            if reachable.insert(idx) {
+                stack.extend(self.get(idx).subschemas());
+            }
+        }
+
+        // sweep others
+        for (i, slot) in self.list.iter_mut().enumerate() {
+            if slot
+                .sch
+                .as_ref()
+                .is_some_and(|sch| !reachable.contains(&sch.idx))
+            {
+                slot.sch = None;
+                slot.gen = slot.gen.wrapping_add(1);
+                self.free.push(i);
+            }
+        }
+        self.map.retain(|_, idx| reachable.contains(idx));
+        true
     }
 
     /**
@@ -178,7 +418,7 @@ impl Schemas {
 
     # Panics
 
-    Panics if `sch_index` is not generated for this instance.
+    Panics if `sch_index` is not generated for this instance, or is removed.
     [`Schemas::contains`] can be used too ensure that it does not panic.
     */
     pub fn validate<'s, 'v>(
@@ -186,13 +426,116 @@ impl Schemas {
         v: &'v Value,
         sch_index: SchemaIndex,
     ) -> Result<(), ValidationError<'s, 'v>> {
-        let Some(sch) = self.list.get(sch_index.0) else {
+        self.validate_with(v, sch_index, &ValidationOptions::default())
+    }
+
+    /**
+    Validates `v` with schema identified by `sch_index`, within limits
+    of `opts`.
+
+    If any limit is exceeded, validation is aborted with
+    [`ErrorKind::LimitExceeded`], irrespective of errors found so far.
+    Similarly cancelled validation fails with [`ErrorKind::Cancelled`].
+
+    ```
+    # use boon::*;
+    # use serde_json::json;
+    # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    compiler.add_resource("schema.json", json!({"items": {"$ref": "#"}}))?;
+    let sch = compiler.compile("schema.json", &mut schemas)?;
+    let opts = ValidationOptions {
+        max_depth: Some(2),
+        ..Default::default()
+    };
+    let v = json!([[[[]]]]);
+    let err = schemas.validate_with(&v, sch, &opts).unwrap_err();
+    assert!(matches!(
+        err.kind,
+        ErrorKind::LimitExceeded {
+            limit: Limit::MaxDepth
+        }
+    ));
+    # Ok(())
+    # }
+    ```
+
+    # Panics
+
+    Panics if `sch_index` is not generated for this instance, or is removed.
+    */
+    pub fn validate_with<'s, 'v>(
+        &'s self,
+        v: &'v Value,
+        sch_index: SchemaIndex,
+        opts: &ValidationOptions,
+    ) -> Result<(), ValidationError<'s, 'v>> {
+        let Some(sch) = self.lookup(sch_index) else {
             panic!("Schemas::validate: schema index out of bounds");
         };
-        validator::validate(v, sch, self)
+        validator::validate(v, sch, self, opts)
     }
 }
 
+/// Limit of [`ValidationOptions`], exceeded in validation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// [`ValidationOptions::max_depth`]
+    MaxDepth,
+    /// [`ValidationOptions::max_steps`]
+    MaxSteps,
+    /// [`ValidationOptions::deadline`]
+    Deadline,
+    /// [`RegexLimits::backtrack_limit`] in matching `pattern` or
+    /// `patternProperties`, with feature `fancy-regex`.
+    BacktrackLimit,
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MaxDepth => write!(f, "max_depth"),
+            Self::MaxSteps => write!(f, "max_steps"),
+            Self::Deadline => write!(f, "deadline"),
+            Self::BacktrackLimit => write!(f, "backtrack_limit"),
+        }
+    }
+}
+
+/**
+Limits on resources used by [`Schemas::validate_with`], to bound
+validation of untrusted instances, and a flag to cancel it.
+
+By default there are no limits.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct ValidationOptions {
+    /// Maximum nesting depth of instance values validated.
+    pub max_depth: Option<usize>,
+    /// Maximum number of schema evaluations.
+    pub max_steps: Option<usize>,
+    /// Validation is aborted after this instant.
+    pub deadline: Option<Instant>,
+    /**
+    Validation is aborted with [`ErrorKind::Cancelled`], once this flag
+    is set, typically from another thread.
+
+    ```
+    # use std::sync::{atomic::{AtomicBool, Ordering}, Arc};
+    # use boon::*;
+    let cancel = Arc::new(AtomicBool::new(false));
+    let opts = ValidationOptions {
+        cancel: Some(Arc::clone(&cancel)),
+        ..Default::default()
+    };
+    // on client disconnect
+    cancel.store(true, Ordering::Relaxed);
+    ```
+    */
+    pub cancel: Option<Arc<AtomicBool>>,
+}
+
 #[derive(Default)]
 struct Schema {
     draft_version: usize,
@@ -212,16 +555,23 @@ struct Schema {
     dynamic_ref: Option<DynamicRef>,
     dynamic_anchor: Option<String>,
     types: Types,
+    type_schemas: Vec<SchemaIndex>,
+    disallow: Types,
+    disallow_schemas: Vec<SchemaIndex>,
     enum_: Option<Enum>,
     constant: Option<Value>,
     not: Option<SchemaIndex>,
     all_of: Vec<SchemaIndex>,
+    extends: Vec<SchemaIndex>,
     any_of: Vec<SchemaIndex>,
     one_of: Vec<SchemaIndex>,
     if_: Option<SchemaIndex>,
     then: Option<SchemaIndex>,
     else_: Option<SchemaIndex>,
     format: Option<Format>,
+    regex_engine: Option<Arc<dyn RegexEngine>>, // validates format `regex`
+    discriminator: Option<Discriminator>,
+    error_message: Option<ErrorMessage>,
 
     // object --
     min_properties: Option<usize>,
@@ -234,12 +584,15 @@ struct Schema {
     dependent_required: Vec<(String, Vec<String>)>,
     dependent_schemas: Vec<(String, SchemaIndex)>,
     dependencies: Vec<(String, Dependency)>,
+    property_dependencies: Vec<(String, AHashMap<String, SchemaIndex>)>,
     unevaluated_properties: Option<SchemaIndex>,
+    embedded_resource: bool, // kubernetes
 
     // array --
     min_items: Option<usize>,
     max_items: Option<usize>,
     unique_items: bool,
+    list_map_keys: Vec<String>, // kubernetes
     min_contains: Option<usize>,
     max_contains: Option<usize>,
     contains: Option<SchemaIndex>,
@@ -291,6 +644,28 @@ enum Dependency {
     SchemaRef(SchemaIndex),
 }
 
+// openapi `discriminator`
+#[derive(Debug)]
+struct Discriminator {
+    prop: String,
+    // value of `prop` to schema. empty if there
+    // is no oneOf/anyOf to choose from.
+    mapping: AHashMap<String, SchemaIndex>,
+}
+
+// `errorMessage`
+#[derive(Debug, Default)]
+struct ErrorMessage {
+    // replaces all errors of the schema
+    all: Option<String>,
+    // keyword to message
+    keywords: AHashMap<String, String>,
+    // missing property to message
+    required: AHashMap<String, String>,
+    // invalid property to message
+    properties: AHashMap<String, String>,
+}
+
 struct DynamicRef {
     sch: SchemaIndex,
     anchor: Option<String>,
@@ -303,6 +678,58 @@ impl Schema {
             ..Default::default()
         }
     }
+
+    // indexes of schemas referenced by this schema
+    fn subschemas(&self) -> Vec<SchemaIndex> {
+        let mut v = vec![self.resource];
+        v.extend(self.dynamic_anchors.values());
+        v.extend(self.ref_);
+        v.extend(self.recursive_ref);
+        v.extend(self.dynamic_ref.as_ref().map(|dref| dref.sch));
+        v.extend(&self.type_schemas);
+        v.extend(&self.disallow_schemas);
+        v.extend(self.not);
+        v.extend(&self.all_of);
+        v.extend(&self.extends);
+        v.extend(&self.any_of);
+        v.extend(&self.one_of);
+        v.extend(self.if_);
+        v.extend(self.then);
+        v.extend(self.else_);
+        if let Some(d) = &self.discriminator {
+            v.extend(d.mapping.values());
+        }
+        v.extend(self.properties.values());
+        v.extend(self.pattern_properties.iter().map(|(_, sch)| *sch));
+        v.extend(self.property_names);
+        if let Some(Additional::SchemaRef(sch)) = &self.additional_properties {
+            v.push(*sch);
+        }
+        v.extend(self.dependent_schemas.iter().map(|(_, sch)| *sch));
+        for (_, dep) in &self.dependencies {
+            if let Dependency::SchemaRef(sch) = dep {
+                v.push(*sch);
+            }
+        }
+        for (_, deps) in &self.property_dependencies {
+            v.extend(deps.values());
+        }
+        v.extend(self.unevaluated_properties);
+        v.extend(self.contains);
+        match &self.items {
+            Some(Items::SchemaRef(sch)) => v.push(*sch),
+            Some(Items::SchemaRefs(list)) => v.extend(list),
+            None => {}
+        }
+        if let Some(Additional::SchemaRef(sch)) = &self.additional_items {
+            v.push(*sch);
+        }
+        v.extend(&self.prefix_items);
+        v.extend(self.items2020);
+        v.extend(self.unevaluated_items);
+        v.extend(self.content_schema);
+        v
+    }
 }
 
 /// JSON data types for JSONSchema
@@ -413,6 +840,8 @@ pub struct ValidationError<'s, 'v> {
     pub instance_location: InstanceLocation<'v>,
     /// kind of error
     pub kind: ErrorKind<'s, 'v>,
+    /// Message from `errorMessage` keyword, which replaces the message of `kind`
+    pub message: Option<Cow<'s, str>>,
     /// Holds nested errors
     pub causes: Vec<ValidationError<'s, 'v>>,
 }
@@ -421,6 +850,7 @@ impl<'s, 'v> Error for ValidationError<'s, 'v> {}
 
 /// A list specifying general categories of validation errors.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum ErrorKind<'s, 'v> {
     Group,
     Schema {
@@ -491,16 +921,25 @@ pub enum ErrorKind<'s, 'v> {
     },
     Contains,
     MinContains {
+        /// indexes of matched items. for objects in draft-next,
+        /// these are positions of matched properties.
         got: Vec<usize>,
         want: usize,
     },
     MaxContains {
+        /// indexes of matched items. for objects in draft-next,
+        /// these are positions of matched properties.
         got: Vec<usize>,
         want: usize,
     },
     UniqueItems {
         got: [usize; 2],
     },
+    /// kubernetes list items at `got` have same values for `keys`.
+    ListMapKeys {
+        keys: &'s [String],
+        got: [usize; 2],
+    },
     AdditionalItems {
         got: usize,
     },
@@ -546,6 +985,14 @@ pub enum ErrorKind<'s, 'v> {
         want: &'s Number,
     },
     Not,
+    /// value matched one of the types or schemas in `disallow`
+    Disallow,
+    /// openapi discriminator property `prop` is missing, or
+    /// its value `got` does not map to any schema.
+    Discriminator {
+        prop: &'s str,
+        got: Option<Cow<'v, Value>>,
+    },
     /// none of the subschemas matched
     AllOf,
     /// none of the subschemas matched.
@@ -553,6 +1000,14 @@ pub enum ErrorKind<'s, 'v> {
     /// - `None`: none of the schemas matched.
     /// - Some(i, j): subschemas at i, j matched
     OneOf(Option<(usize, usize)>),
+    /// validation is aborted, as `limit` field of [`ValidationOptions`]
+    /// is exceeded.
+    LimitExceeded {
+        limit: Limit,
+    },
+    /// validation is aborted, as `cancel` flag of [`ValidationOptions`]
+    /// is set.
+    Cancelled,
 }
 
 impl<'s, 'v> Display for ErrorKind<'s, 'v> {
@@ -671,6 +1126,11 @@ impl<'s, 'v> Display for ErrorKind<'s, 'v> {
                     )
             }
             Self::UniqueItems { got: [i, j] } => write!(f, "items at {i} and {j} are equal"),
+            Self::ListMapKeys { keys, got: [i, j] } => write!(
+                f,
+                "items at {i} and {j} have same values for keys {}",
+                join_iter(keys.iter().map(quote), ", ")
+            ),
             Self::AdditionalItems { got } => write!(f, "last {got} additionalItems not allowed"),
             Self::MinLength { got, want } => write!(f, "length must be >={want}, but got {got}"),
             Self::MaxLength { got, want } => write!(f, "length must be <={want}, but got {got}"),
@@ -689,10 +1149,23 @@ impl<'s, 'v> Display for ErrorKind<'s, 'v> {
             Self::ExclusiveMaximum { got, want } => write!(f, "must be < {want} but got {got}"),
             Self::MultipleOf { got, want } => write!(f, "{got} is not multipleOf {want}"),
             Self::Not => write!(f, "not failed"),
+            Self::Disallow => write!(f, "disallow failed"),
+            Self::Discriminator { prop, got: None } => {
+                write!(f, "missing discriminator property {}", quote(prop))
+            }
+            Self::Discriminator {
+                prop,
+                got: Some(got),
+            } => {
+                display(f, got)?;
+                write!(f, " is not valid value for discriminator {}", quote(prop))
+            }
             Self::AllOf => write!(f, "allOf failed",),
             Self::AnyOf => write!(f, "anyOf failed"),
             Self::OneOf(None) => write!(f, "oneOf failed, none matched"),
             Self::OneOf(Some((i, j))) => write!(f, "oneOf failed, subschemas {i}, {j} matched"),
+            Self::LimitExceeded { limit } => write!(f, "validation aborted, {limit} exceeded"),
+            Self::Cancelled => write!(f, "validation cancelled"),
         }
     }
 }
@@ -1,18 +1,328 @@
-use std::{cell::RefCell, collections::HashMap, error::Error};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt::Display,
+    sync::Arc,
+};
 
 #[cfg(not(target_arch = "wasm32"))]
-use std::fs::File;
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 
 use once_cell::sync::Lazy;
 use serde_json::Value;
 use url::Url;
 
-use crate::{compiler::CompileError, draft::latest};
+use crate::{
+    cache::DocumentCache,
+    compiler::CompileError,
+    draft::latest,
+    source::{ParseError, Position, Source, SourceFormat, SourceMap},
+};
 
-/// A trait for loading json from given `url`
+/**
+A trait for loading json from given `url`.
+
+Implement either [`UrlLoader::load`] to return parsed json, or
+[`UrlLoader::load_content`] to return raw content and let this crate
+choose the parser from its media type or extension.
+*/
 pub trait UrlLoader {
-    /// Loads json from given absolute `url`.
-    fn load(&self, url: &str) -> Result<Value, Box<dyn Error>>;
+    /**
+    Loads json from given absolute `url`.
+
+    Default implementation parses the content returned by
+    [`UrlLoader::load_content`].
+
+    **Warning:** implementors must override either this method or
+    [`UrlLoader::load_content`]. Otherwise loading fails with error.
+    */
+    fn load(&self, url: &str) -> Result<Value, Box<dyn Error>> {
+        let Some(content) = self.load_content(url)? else {
+            return Err("UrlLoader must implement load or load_content".into());
+        };
+        Ok(content.parse(&Url::parse(url)?)?.0)
+    }
+
+    /**
+    Loads json from given absolute `url`, along with positions of its values.
+
+    The positions are used to report line and column of compile errors.
+    Compiler calls it only if [`UrlLoader::load_content`] returns `None`.
+    Default implementation calls [`UrlLoader::load`] and returns empty
+    [`SourceMap`].
+    */
+    fn load_with_source_map(&self, url: &str) -> Result<(Value, SourceMap), Box<dyn Error>> {
+        Ok((self.load(url)?, SourceMap::default()))
+    }
+
+    /**
+    Loads raw content from given absolute `url`.
+
+    Default implementation returns `None`, in which case [`UrlLoader::load`]
+    is used.
+    */
+    fn load_content(&self, url: &str) -> Result<Option<UrlContent>, Box<dyn Error>> {
+        _ = url;
+        Ok(None)
+    }
+
+    /**
+    Loads raw content from given absolute `url`, reading at most `limit + 1`
+    bytes, when [`LoadPolicy::max_document_size`] is set.
+
+    Content longer than `limit` is rejected, so loaders may return it
+    truncated. Default implementation calls [`UrlLoader::load_content`].
+    */
+    fn load_content_with_limit(
+        &self,
+        url: &str,
+        limit: usize,
+    ) -> Result<Option<UrlContent>, Box<dyn Error>> {
+        _ = limit;
+        self.load_content(url)
+    }
+
+    /**
+    Checks that this loader may serve given absolute `url`.
+
+    It is called before the document is taken from [`DocumentCache`],
+    which may be shared with compilers using other loaders. Loaders
+    restricting access must implement it. Default implementation allows
+    all urls.
+
+    [`DocumentCache`]: crate::DocumentCache
+    */
+    fn check_url(&self, url: &str) -> Result<(), Box<dyn Error>> {
+        _ = url;
+        Ok(())
+    }
+}
+
+/**
+Raw content loaded by [`UrlLoader::load_content`].
+
+The parser is chosen using the first known of: explicit format, media
+type, extension hint and extension of url; defaulting to json.
+
+```
+# use boon::*;
+let content = UrlContent::new("type: object").with_media_type("application/yaml");
+```
+*/
+#[derive(Debug, Clone)]
+pub struct UrlContent {
+    bytes: Vec<u8>,
+    media_type: Option<String>,
+    format: Option<SourceFormat>,
+    extension: Option<SourceFormat>,
+}
+
+impl UrlContent {
+    pub fn new(bytes: impl Into<Vec<u8>>) -> Self {
+        Self {
+            bytes: bytes.into(),
+            media_type: None,
+            format: None,
+            extension: None,
+        }
+    }
+
+    /// Sets media type of content, for example `Content-Type` of http response.
+    pub fn with_media_type(mut self, media_type: impl Into<String>) -> Self {
+        self.media_type = Some(media_type.into());
+        self
+    }
+
+    /// Sets file extension hint, for urls without extension.
+    pub fn with_extension(mut self, ext: &str) -> Self {
+        self.extension = SourceFormat::from_extension(ext);
+        self
+    }
+
+    /// Sets format of content explicitly.
+    pub fn with_format(mut self, format: SourceFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Returns raw bytes of content.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns format used to parse this content loaded from `url`.
+    pub fn format(&self, url: &Url) -> SourceFormat {
+        self.format
+            .or_else(|| {
+                let media_type = self.media_type.as_deref()?;
+                SourceFormat::from_media_type(media_type)
+            })
+            .or(self.extension)
+            .or_else(|| SourceFormat::from_path(url.path()))
+            .unwrap_or(SourceFormat::Json)
+    }
+
+    pub(crate) fn parse(&self, url: &Url) -> Result<(Value, Source), Box<dyn Error>> {
+        let text = std::str::from_utf8(&self.bytes)?;
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        parse(url, text, Some(self.format(url)))
+    }
+}
+
+// --
+
+/**
+Restrictions on documents loaded by [`UrlLoader`]s, to compile untrusted
+schemas safely. See [`Compiler::set_load_policy`].
+
+Documents added with [`Compiler::add_resource`] and standard meta-schemas
+are always allowed. Urls mapped with [`Compiler::map_url_prefix`] are
+checked before mapping. Violations fail with [`CompileError::UrlNotAllowed`].
+
+By default everything is allowed.
+
+```
+# use boon::*;
+let mut compiler = Compiler::new();
+compiler.set_load_policy(LoadPolicy {
+    allowed_schemes: Some(vec!["https".to_owned()]),
+    allowed_prefixes: Some(vec!["https://schemas.example.com/".to_owned()]),
+    max_documents: Some(50),
+    max_document_size: Some(1 << 20),
+});
+```
+
+[`Compiler::set_load_policy`]: crate::Compiler::set_load_policy
+[`Compiler::add_resource`]: crate::Compiler::add_resource
+[`Compiler::map_url_prefix`]: crate::Compiler::map_url_prefix
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadPolicy {
+    /// Url schemes allowed, for example `https`.
+    pub allowed_schemes: Option<Vec<String>>,
+    /**
+    Urls allowed, by their prefix, for example `https://example.com/schemas/`.
+
+    Prefix matches only at segment boundary, so `https://example.com`
+    does not allow `https://example.com.org/`.
+    */
+    pub allowed_prefixes: Option<Vec<String>>,
+    /// Maximum number of documents loaded.
+    pub max_documents: Option<usize>,
+    /**
+    Maximum size of document in bytes.
+
+    Size of content returned by [`UrlLoader::load_content_with_limit`] is
+    checked before parsing; files are not read beyond the limit. For loaders
+    returning parsed json, size of its serialized json is checked.
+    */
+    pub max_document_size: Option<usize>,
+}
+
+impl LoadPolicy {
+    // returns reason, if loading `url` is not allowed
+    fn check_url(&self, url: &Url) -> Option<String> {
+        if let Some(schemes) = &self.allowed_schemes {
+            if !schemes.iter().any(|s| s.eq_ignore_ascii_case(url.scheme())) {
+                return Some(format!("scheme {} is not allowed", url.scheme()));
+            }
+        }
+        if let Some(prefixes) = &self.allowed_prefixes {
+            if !prefixes.iter().any(|p| has_prefix(url.as_str(), p)) {
+                return Some("url does not match allowed prefixes".to_owned());
+            }
+        }
+        None
+    }
+}
+
+fn has_prefix(url: &str, prefix: &str) -> bool {
+    let Some(rest) = url.strip_prefix(prefix) else {
+        return false;
+    };
+    prefix.ends_with(['/', '?', '#']) || rest.is_empty() || rest.starts_with(['/', '?', '#'])
+}
+
+// returns size of `v` serialized as json
+fn json_size(v: &Value) -> usize {
+    serde_json::to_vec(v).map_or(0, |b| b.len())
+}
+
+// --
+
+/**
+Parses source `text` of document at `url`.
+
+If `format` is `None`, it is detected from extension of `url`, defaulting
+to json. Text with multiple documents requires query `document=<index>`
+in `url` to select one of them.
+*/
+pub(crate) fn parse(
+    url: &Url,
+    text: &str,
+    format: Option<SourceFormat>,
+) -> Result<(Value, Source), Box<dyn Error>> {
+    let mut docs = parse_all(url, text, format)?;
+    match document_index(url)? {
+        None if docs.len() == 1 => Ok(docs.remove(0)),
+        None => Err(format!(
+            "found {} documents, use query document=<index> to select one",
+            docs.len()
+        )
+        .into()),
+        Some(i) if i < docs.len() => Ok(docs.swap_remove(i)),
+        Some(i) => Err(format!("document {i} not found, got {}", docs.len()).into()),
+    }
+}
+
+// parses all documents in `text`, keeping the text to compute their
+// positions when needed
+pub(crate) fn parse_all(
+    url: &Url,
+    text: &str,
+    format: Option<SourceFormat>,
+) -> Result<Vec<(Value, Source)>, ParseError> {
+    let format = format
+        .or_else(|| SourceFormat::from_path(url.path()))
+        .unwrap_or(SourceFormat::Json);
+    let values = SourceMap::parse_values(text, format)?;
+    let text: Arc<str> = text.into();
+    let docs = values.into_iter().enumerate();
+    Ok(docs
+        .map(|(i, v)| (v, Source::new(Arc::clone(&text), format, i)))
+        .collect())
+}
+
+fn document_index(url: &Url) -> Result<Option<usize>, Box<dyn Error>> {
+    match url.query_pairs().find(|(k, _)| k == "document") {
+        Some((_, v)) => Ok(Some(v.parse()?)),
+        None => Ok(None),
+    }
+}
+
+// --
+
+/// Document loaded by [`UrlLoader`].
+#[derive(Debug)]
+pub(crate) struct Document {
+    pub(crate) value: Value,
+    pub(crate) source: Option<Arc<Source>>,
+    pub(crate) size: Option<usize>, // in bytes, if known
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) mtime: Option<(PathBuf, SystemTime)>, // of file, before it is read
+}
+
+impl Document {
+    // returns size in bytes, computing it if not known
+    fn size(&self) -> usize {
+        self.size.unwrap_or_else(|| json_size(&self.value))
+    }
 }
 
 // --
@@ -20,13 +330,136 @@ pub trait UrlLoader {
 #[cfg(not(target_arch = "wasm32"))]
 struct FileLoader;
 
+#[cfg(not(target_arch = "wasm32"))]
+impl FileLoader {
+    fn read(&self, url: &str, limit: Option<usize>) -> Result<Option<UrlContent>, Box<dyn Error>> {
+        let url = Url::parse(url)?;
+        let path = url.to_file_path().map_err(|_| "invalid file path")?;
+        Ok(Some(UrlContent::new(read_file(&path, limit)?)))
+    }
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 impl UrlLoader for FileLoader {
-    fn load(&self, url: &str) -> Result<Value, Box<dyn Error>> {
+    fn load_content(&self, url: &str) -> Result<Option<UrlContent>, Box<dyn Error>> {
+        self.read(url, None)
+    }
+
+    fn load_content_with_limit(
+        &self,
+        url: &str,
+        limit: usize,
+    ) -> Result<Option<UrlContent>, Box<dyn Error>> {
+        self.read(url, Some(limit))
+    }
+}
+
+/// Error from reading file larger than limit.
+#[derive(Debug)]
+struct FileTooLarge(u64);
+
+impl Display for FileTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "file size {} exceeds limit", self.0)
+    }
+}
+
+impl Error for FileTooLarge {}
+
+// reads file at `path`, failing if it is larger than `limit` bytes
+#[cfg(not(target_arch = "wasm32"))]
+fn read_file(path: &Path, limit: Option<usize>) -> Result<Vec<u8>, Box<dyn Error>> {
+    let Some(limit) = limit else {
+        return Ok(fs::read(path)?);
+    };
+    let limit = limit as u64;
+    let file = fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    if len > limit {
+        return Err(FileTooLarge(len).into());
+    }
+    // file may grow meanwhile, or be special file like /dev/zero
+    let mut bytes = vec![];
+    file.take(limit + 1).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > limit {
+        return Err(FileTooLarge(bytes.len() as u64).into());
+    }
+    Ok(bytes)
+}
+
+/**
+[`UrlLoader`] for `file` urls, serving only files under `root` directory.
+
+Files outside `root`, including those reached through symlinks escaping
+it, fail to load. Register it for `file` scheme to replace the default
+loader:
+
+```no_run
+# use boon::*;
+let mut compiler = Compiler::new();
+compiler.register_url_loader("file", Box::new(SandboxedFileLoader::new("/srv/tenant1")?));
+# Ok::<(), std::io::Error>(())
+```
+*/
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct SandboxedFileLoader {
+    root: PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SandboxedFileLoader {
+    /**
+    Creates loader serving files under `root`.
+
+    # Errors
+
+    returns [`std::io::Error`] if `root` cannot be resolved.
+    */
+    pub fn new(root: impl AsRef<Path>) -> std::io::Result<Self> {
+        Ok(Self {
+            root: fs::canonicalize(root)?,
+        })
+    }
+
+    /// Returns canonical path of root directory.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn read(&self, url: &str, limit: Option<usize>) -> Result<Option<UrlContent>, Box<dyn Error>> {
+        Ok(Some(UrlContent::new(read_file(&self.path(url)?, limit)?)))
+    }
+
+    // returns canonical path of file at `url`, if it is under root
+    fn path(&self, url: &str) -> Result<PathBuf, Box<dyn Error>> {
         let url = Url::parse(url)?;
         let path = url.to_file_path().map_err(|_| "invalid file path")?;
-        let file = File::open(path)?;
-        Ok(serde_json::from_reader(file)?)
+        // resolves `..` and symlinks
+        let path = fs::canonicalize(path)?;
+        if !path.starts_with(&self.root) {
+            return Err(format!("{} is outside {}", path.display(), self.root.display()).into());
+        }
+        Ok(path)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl UrlLoader for SandboxedFileLoader {
+    fn load_content(&self, url: &str) -> Result<Option<UrlContent>, Box<dyn Error>> {
+        self.read(url, None)
+    }
+
+    fn load_content_with_limit(
+        &self,
+        url: &str,
+        limit: usize,
+    ) -> Result<Option<UrlContent>, Box<dyn Error>> {
+        self.read(url, Some(limit))
+    }
+
+    fn check_url(&self, url: &str) -> Result<(), Box<dyn Error>> {
+        self.path(url).map(|_| ())
     }
 }
 
@@ -34,28 +467,216 @@ impl UrlLoader for FileLoader {
 
 pub(crate) struct DefaultUrlLoader {
     resources: RefCell<HashMap<Url, Value>>,
+    loaded_resources: RefCell<HashSet<Url>>,
+    sources: RefCell<HashMap<Url, Arc<Source>>>, // to compute positions
+    #[cfg(not(target_arch = "wasm32"))]
+    mtimes: RefCell<HashMap<Url, (PathBuf, SystemTime)>>, // modification time of loaded files
+    source_format: Option<SourceFormat>,
+    url_prefixes: Vec<(String, Url)>, // prefix => dir
+    cache: Option<DocumentCache>,
     loaders: HashMap<&'static str, Box<dyn UrlLoader>>,
+    policy: LoadPolicy,
+    fetched: RefCell<HashSet<Url>>, // documents loaded by loaders, counted by policy
 }
 
 impl DefaultUrlLoader {
     pub fn new() -> Self {
         let mut v = Self {
             resources: Default::default(),
+            loaded_resources: Default::default(),
+            sources: Default::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            mtimes: Default::default(),
+            source_format: Default::default(),
+            url_prefixes: Default::default(),
+            cache: None,
             loaders: Default::default(),
+            policy: Default::default(),
+            fetched: Default::default(),
         };
         #[cfg(not(target_arch = "wasm32"))]
         v.loaders.insert("file", Box::new(FileLoader));
         v
     }
 
+    pub fn set_source_format(&mut self, format: SourceFormat) {
+        self.source_format = Some(format);
+    }
+
+    // format overriding that of content loaded from `load_url`
+    fn format(&self, load_url: &Url) -> Option<SourceFormat> {
+        self.source_format.filter(|_| load_url.scheme() == "file")
+    }
+
     pub fn add_resource(&mut self, url: Url, json: Value) {
         self.resources.get_mut().insert(url, json);
     }
 
+    pub fn add_resource_text(&mut self, url: Url, text: &str) -> Result<(), CompileError> {
+        let to_error = |src| CompileError::LoadUrlError {
+            url: url.to_string(),
+            src,
+        };
+        if document_index(&url).map_err(to_error)?.is_some() {
+            let doc = parse(&url, text, self.source_format).map_err(to_error)?;
+            self.add_parsed(url, doc);
+            return Ok(());
+        }
+        let mut docs = parse_all(&url, text, self.source_format).map_err(|e| to_error(e.into()))?;
+        if docs.len() == 1 {
+            self.add_parsed(url, docs.remove(0));
+            return Ok(());
+        }
+        for (i, doc) in docs.into_iter().enumerate() {
+            let mut url = url.clone();
+            url.query_pairs_mut()
+                .append_pair("document", &i.to_string());
+            self.add_parsed(url, doc);
+        }
+        Ok(())
+    }
+
+    fn add_parsed(&mut self, url: Url, (json, source): (Value, Source)) {
+        self.sources.get_mut().insert(url.clone(), Arc::new(source));
+        self.resources.get_mut().insert(url, json);
+    }
+
+    pub fn map_url_prefix(&mut self, prefix: String, mut dir: Url) {
+        if !dir.path().ends_with('/') {
+            dir.set_path(&format!("{}/", dir.path()));
+        }
+        self.url_prefixes.retain(|(p, _)| *p != prefix);
+        self.url_prefixes.push((prefix, dir));
+        // longest prefix first
+        self.url_prefixes
+            .sort_by_key(|(p, _)| std::cmp::Reverse(p.len()));
+    }
+
+    // returns url with its prefix replaced by mapped dir
+    fn mapped_url(&self, url: &Url) -> Result<Option<Url>, CompileError> {
+        let found = self
+            .url_prefixes
+            .iter()
+            .filter(|(prefix, _)| has_prefix(url.as_str(), prefix))
+            .find_map(|(prefix, dir)| Some((url.as_str().strip_prefix(prefix.as_str())?, dir)));
+        let Some((rest, dir)) = found else {
+            return Ok(None);
+        };
+        let mapped = format!("{dir}{}", rest.trim_start_matches('/'));
+        match Url::parse(&mapped) {
+            Ok(url) => Ok(Some(url)),
+            Err(e) => Err(CompileError::ParseUrlError {
+                url: mapped,
+                src: e.into(),
+            }),
+        }
+    }
+
+    pub fn set_cache(&mut self, cache: DocumentCache) {
+        self.cache = Some(cache);
+    }
+
     pub fn register(&mut self, schema: &'static str, loader: Box<dyn UrlLoader>) {
         self.loaders.insert(schema, loader);
     }
 
+    pub fn set_policy(&mut self, policy: LoadPolicy) {
+        self.policy = policy;
+    }
+
+    // checks `url` against policy, before it is fetched
+    fn check_policy(&self, url: &Url) -> Result<(), CompileError> {
+        let not_allowed = |reason| CompileError::UrlNotAllowed {
+            url: url.as_str().to_owned(),
+            reason,
+        };
+        if let Some(reason) = self.policy.check_url(url) {
+            return Err(not_allowed(reason));
+        }
+        let fetched = self.fetched.borrow();
+        if let Some(max) = self.policy.max_documents {
+            if !fetched.contains(url) && fetched.len() >= max {
+                return Err(not_allowed(format!("exceeds max_documents {max}")));
+            }
+        }
+        Ok(())
+    }
+
+    // loads document using `loader`, checking its size against policy
+    fn fetch(
+        &self,
+        loader: &dyn UrlLoader,
+        url: &Url,
+        load_url: &Url,
+    ) -> Result<Document, CompileError> {
+        let to_error = |src| CompileError::LoadUrlError {
+            url: load_url.as_str().to_owned(),
+            src,
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        let mtime = file_mtime(load_url);
+        let content = match self.policy.max_document_size {
+            None => loader.load_content(load_url.as_str()).map_err(to_error)?,
+            Some(limit) => match loader.load_content_with_limit(load_url.as_str(), limit) {
+                Ok(content) => content,
+                Err(src) => match src.downcast_ref::<FileTooLarge>() {
+                    Some(e) => return Err(self.size_error(url, e.0 as usize)),
+                    None => return Err(to_error(src)),
+                },
+            },
+        };
+        let Some(content) = content else {
+            let (value, source_map) = loader
+                .load_with_source_map(load_url.as_str())
+                .map_err(to_error)?;
+            let size = self.policy.max_document_size.map(|_| json_size(&value));
+            if let Some(size) = size {
+                self.check_size(url, size)?;
+            }
+            let source = (!source_map.is_empty()).then(|| Source::from_map(source_map));
+            return Ok(Document {
+                value,
+                source: source.map(Arc::new),
+                size,
+                #[cfg(not(target_arch = "wasm32"))]
+                mtime,
+            });
+        };
+        let size = content.bytes().len();
+        self.check_size(url, size)?;
+        let content = self.with_format(load_url, content);
+        let (value, source) = content.parse(load_url).map_err(to_error)?;
+        Ok(Document {
+            value,
+            source: Some(Arc::new(source)),
+            size: Some(size),
+            #[cfg(not(target_arch = "wasm32"))]
+            mtime,
+        })
+    }
+
+    fn with_format(&self, load_url: &Url, content: UrlContent) -> UrlContent {
+        match self.format(load_url) {
+            Some(format) => content.with_format(format),
+            None => content,
+        }
+    }
+
+    fn check_size(&self, url: &Url, size: usize) -> Result<(), CompileError> {
+        match self.policy.max_document_size {
+            Some(max) if size > max => Err(self.size_error(url, size)),
+            _ => Ok(()),
+        }
+    }
+
+    fn size_error(&self, url: &Url, size: usize) -> CompileError {
+        let max = self.policy.max_document_size.unwrap_or_default();
+        CompileError::UrlNotAllowed {
+            url: url.as_str().to_owned(),
+            reason: format!("document size {size} exceeds max_document_size {max}"),
+        }
+    }
+
     pub(crate) fn load(&self, url: &Url) -> Result<Value, CompileError> {
         // check in STD_METAFILES
         let meta = url
@@ -78,21 +699,119 @@ impl DefaultUrlLoader {
         }
 
         if let Some(v) = self.resources.borrow_mut().remove(url) {
+            self.loaded_resources.borrow_mut().insert(url.clone());
             return Ok(v);
         }
 
-        match self.loaders.get(url.scheme()) {
-            Some(loader) => loader
-                .load(url.as_str())
-                .map_err(|src| CompileError::LoadUrlError {
-                    url: url.as_str().to_owned(),
-                    src,
-                }),
-            None => Err(CompileError::UnsupportedUrlScheme {
-                url: url.as_str().to_owned(),
-            }),
+        // document is loaded from mapped url, but keeps its original url
+        self.check_policy(url)?;
+        let mapped = self.mapped_url(url)?;
+        let load_url = mapped.as_ref().unwrap_or(url);
+        let Some(loader) = self.loaders.get(load_url.scheme()) else {
+            return Err(CompileError::UnsupportedUrlScheme {
+                url: load_url.as_str().to_owned(),
+            });
+        };
+        // cache may be shared with compilers using other loaders
+        loader
+            .check_url(load_url.as_str())
+            .map_err(|src| CompileError::LoadUrlError {
+                url: load_url.as_str().to_owned(),
+                src,
+            })?;
+        let format = self.format(load_url);
+        let loaded = match self.cache.as_ref().and_then(|c| c.get(load_url, format)) {
+            Some(loaded) => {
+                if self.policy.max_document_size.is_some() {
+                    // cache may be shared with compilers without policy
+                    self.check_size(url, loaded.size())?;
+                }
+                loaded
+            }
+            None => {
+                let loaded = Arc::new(self.fetch(loader.as_ref(), url, load_url)?);
+                if let Some(cache) = &self.cache {
+                    cache.insert(load_url.clone(), format, Arc::clone(&loaded));
+                }
+                loaded
+            }
+        };
+        self.fetched.borrow_mut().insert(url.clone());
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(mtime) = &loaded.mtime {
+            self.mtimes.borrow_mut().insert(url.clone(), mtime.clone());
+        }
+        if let Some(source) = &loaded.source {
+            self.sources
+                .borrow_mut()
+                .insert(url.clone(), Arc::clone(source));
+        }
+        Ok(loaded.value.clone())
+    }
+
+    /// Releases document loaded from `url`. Resources are kept, so that
+    /// they can be loaded again, unless replaced meanwhile.
+    pub(crate) fn unload(&mut self, url: &Url, doc: Value) {
+        self.fetched.get_mut().remove(url);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.mtimes.get_mut().remove(url);
+        if !self.restore(url, doc) {
+            self.sources.get_mut().remove(url);
+        }
+    }
+
+    /// Adds back resource loaded from `url`, unless replaced meanwhile.
+    /// Returns false if document at `url` is not a resource.
+    pub(crate) fn restore(&mut self, url: &Url, doc: Value) -> bool {
+        if !self.loaded_resources.get_mut().remove(url) {
+            return false;
+        }
+        self.resources.get_mut().entry(url.clone()).or_insert(doc);
+        true
+    }
+
+    /// Removes document loaded from `url` from cache, so that it is read again.
+    pub(crate) fn uncache(&self, url: &Url) -> Result<(), CompileError> {
+        if let Some(cache) = &self.cache {
+            let mapped = self.mapped_url(url)?;
+            cache.remove(mapped.as_ref().unwrap_or(url));
         }
+        Ok(())
+    }
+
+    /// Returns urls of loaded files, modified since they are loaded.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn modified_files(&self) -> Vec<Url> {
+        let mtimes = self.mtimes.borrow();
+        let modified = mtimes.iter().filter(|(_, (path, mtime))| {
+            fs::metadata(path)
+                .and_then(|m| m.modified())
+                .map_or(true, |m| m != *mtime)
+        });
+        modified.map(|(url, _)| url.clone()).collect()
+    }
+
+    /// Returns position of value at `ptr` in document loaded from `url`.
+    pub(crate) fn position(&self, url: &Url, ptr: &str) -> Option<Position> {
+        self.sources.borrow().get(url)?.map().get(ptr)
+    }
+
+    /// Returns source map of value at `ptr` in document loaded from `url`.
+    pub(crate) fn source_map(&self, url: &Url, ptr: &str) -> Option<SourceMap> {
+        let map = self.sources.borrow().get(url)?.map().subtree(ptr);
+        (!map.is_empty()).then_some(map)
+    }
+}
+
+// returns modification time of file at `url`, if it is file url
+#[cfg(not(target_arch = "wasm32"))]
+fn file_mtime(url: &Url) -> Option<(PathBuf, SystemTime)> {
+    if url.scheme() != "file" {
+        return None;
     }
+    let path = url.to_file_path().ok()?;
+    let mtime = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+    Some((path, mtime))
 }
 
 pub(crate) static STD_METAFILES: Lazy<HashMap<String, &str>> = Lazy::new(|| {
@@ -105,6 +824,7 @@ pub(crate) static STD_METAFILES: Lazy<HashMap<String, &str>> = Lazy::new(|| {
             );
         };
     }
+    add!("metaschemas/draft-03/schema");
     add!("metaschemas/draft-04/schema");
     add!("metaschemas/draft-06/schema");
     add!("metaschemas/draft-07/schema");
@@ -124,5 +844,14 @@ pub(crate) static STD_METAFILES: Lazy<HashMap<String, &str>> = Lazy::new(|| {
     add!("metaschemas/draft/2020-12/meta/content");
     add!("metaschemas/draft/2020-12/meta/format-annotation");
     add!("metaschemas/draft/2020-12/meta/format-assertion");
+    add!("metaschemas/draft/next/schema");
+    add!("metaschemas/draft/next/meta/core");
+    add!("metaschemas/draft/next/meta/applicator");
+    add!("metaschemas/draft/next/meta/unevaluated");
+    add!("metaschemas/draft/next/meta/validation");
+    add!("metaschemas/draft/next/meta/meta-data");
+    add!("metaschemas/draft/next/meta/content");
+    add!("metaschemas/draft/next/meta/format-annotation");
+    add!("metaschemas/draft/next/meta/format-assertion");
     files
 });
@@ -0,0 +1,253 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{util::*, ErrorKind};
+
+/**
+Provides message templates for [`ErrorKind`]s, to translate or
+rephrase the default english messages.
+
+Templates refer to the parameters of error using `{name}`, where
+`name` is a field of the [`ErrorKind`] variant; see [`ErrorKind::params`].
+Use `{{` and `}}` for literal braces.
+
+# Examples
+
+```
+# use boon::*;
+# use serde_json::json;
+struct German;
+
+impl MessageCatalog for German {
+    fn template(&self, kind: &ErrorKind) -> Option<&str> {
+        match kind {
+            ErrorKind::Required { .. } => Some("fehlende Eigenschaften {want}"),
+            _ => None,
+        }
+    }
+}
+
+let mut schemas = Schemas::new();
+let mut compiler = Compiler::new();
+compiler.add_resource("schema.json", json!({"required": ["name"]})).unwrap();
+let sch = compiler.compile("schema.json", &mut schemas).unwrap();
+let instance = json!({});
+let err = schemas.validate(&instance, sch).unwrap_err();
+assert!(err.localize(&German).to_string().ends_with("fehlende Eigenschaften 'name'"));
+```
+
+`HashMap<String, String>` implements this trait, with keys
+from [`ErrorKind::id`]. This is useful to load catalogs from files.
+*/
+pub trait MessageCatalog {
+    /// Returns template for `kind`. `None` uses the default message.
+    fn template(&self, kind: &ErrorKind) -> Option<&str>;
+}
+
+impl MessageCatalog for HashMap<String, String> {
+    fn template(&self, kind: &ErrorKind) -> Option<&str> {
+        self.get(kind.id()).map(String::as_str)
+    }
+}
+
+impl<'s, 'v> ErrorKind<'s, 'v> {
+    /// Returns identifier of this kind, which is its variant name in camelCase.
+    pub fn id(&self) -> &'static str {
+        use ErrorKind::*;
+        match self {
+            Group => "group",
+            Schema { .. } => "schema",
+            ContentSchema => "contentSchema",
+            PropertyName { .. } => "propertyName",
+            Reference { .. } => "reference",
+            RefCycle { .. } => "refCycle",
+            FalseSchema => "falseSchema",
+            Type { .. } => "type",
+            Enum { .. } => "enum",
+            Const { .. } => "const",
+            Format { .. } => "format",
+            MinProperties { .. } => "minProperties",
+            MaxProperties { .. } => "maxProperties",
+            AdditionalProperties { .. } => "additionalProperties",
+            Required { .. } => "required",
+            Dependency { .. } => "dependency",
+            DependentRequired { .. } => "dependentRequired",
+            MinItems { .. } => "minItems",
+            MaxItems { .. } => "maxItems",
+            Contains => "contains",
+            MinContains { .. } => "minContains",
+            MaxContains { .. } => "maxContains",
+            UniqueItems { .. } => "uniqueItems",
+            ListMapKeys { .. } => "listMapKeys",
+            AdditionalItems { .. } => "additionalItems",
+            MinLength { .. } => "minLength",
+            MaxLength { .. } => "maxLength",
+            Pattern { .. } => "pattern",
+            ContentEncoding { .. } => "contentEncoding",
+            ContentMediaType { .. } => "contentMediaType",
+            Minimum { .. } => "minimum",
+            Maximum { .. } => "maximum",
+            ExclusiveMinimum { .. } => "exclusiveMinimum",
+            ExclusiveMaximum { .. } => "exclusiveMaximum",
+            MultipleOf { .. } => "multipleOf",
+            Not => "not",
+            Disallow => "disallow",
+            Discriminator { .. } => "discriminator",
+            AllOf => "allOf",
+            AnyOf => "anyOf",
+            OneOf(_) => "oneOf",
+            LimitExceeded { .. } => "limitExceeded",
+            Cancelled => "cancelled",
+        }
+    }
+
+    /**
+    Returns parameters of this kind, used in message templates.
+
+    Parameters are named after the fields of the variant. Strings are
+    quoted and lists are joined with `, `, as in the default messages.
+    For [`ErrorKind::OneOf`] and [`ErrorKind::UniqueItems`], `got` has
+    the indexes of matched subschemas and equal items respectively.
+    */
+    pub fn params(&self) -> Vec<(&'static str, String)> {
+        fn list<T: Display>(iter: impl IntoIterator<Item = T>) -> String {
+            join_iter(iter, ", ")
+        }
+        fn quoted<T: AsRef<str>>(iter: impl IntoIterator<Item = T>) -> String {
+            list(iter.into_iter().map(|s| quote(s.as_ref())))
+        }
+
+        use ErrorKind::*;
+        match self {
+            Group | ContentSchema | FalseSchema | Contains | Cancelled => vec![],
+            Not | Disallow | AllOf | AnyOf | OneOf(None) => vec![],
+            Schema { url } => vec![("url", url.to_string())],
+            PropertyName { prop } => vec![("prop", quote(prop))],
+            Reference { kw, url } => vec![("kw", kw.to_string()), ("url", url.to_string())],
+            RefCycle {
+                url,
+                kw_loc1,
+                kw_loc2,
+            } => vec![
+                ("url", url.to_string()),
+                ("kw_loc1", quote(kw_loc1)),
+                ("kw_loc2", quote(kw_loc2)),
+            ],
+            Type { got, want } => vec![
+                ("got", got.to_string()),
+                ("want", join_iter(want.iter(), " or ")),
+            ],
+            Enum { want } => vec![("want", list(want.iter().map(value)))],
+            Const { want } => vec![("want", value(want))],
+            Format { got, want, err } => vec![
+                ("got", value(got)),
+                ("want", want.to_string()),
+                ("err", err.to_string()),
+            ],
+            MinProperties { got, want }
+            | MaxProperties { got, want }
+            | MinItems { got, want }
+            | MaxItems { got, want }
+            | MinLength { got, want }
+            | MaxLength { got, want } => {
+                vec![("got", got.to_string()), ("want", want.to_string())]
+            }
+            AdditionalProperties { got } => vec![("got", quoted(got))],
+            Required { want } => vec![("want", quoted(want))],
+            Dependency { prop, missing } | DependentRequired { prop, missing } => {
+                vec![("prop", quote(prop)), ("missing", quoted(missing))]
+            }
+            MinContains { got, want } | MaxContains { got, want } => {
+                vec![("got", list(got)), ("want", want.to_string())]
+            }
+            UniqueItems { got } => vec![("got", list(got))],
+            ListMapKeys { keys, got } => vec![("keys", quoted(keys.iter())), ("got", list(got))],
+            AdditionalItems { got } => vec![("got", got.to_string())],
+            Pattern { got, want } => vec![("got", quote(got)), ("want", quote(want))],
+            ContentEncoding { want, err } | ContentMediaType { want, err, .. } => {
+                vec![("want", quote(want)), ("err", err.to_string())]
+            }
+            Minimum { got, want }
+            | Maximum { got, want }
+            | ExclusiveMinimum { got, want }
+            | ExclusiveMaximum { got, want }
+            | MultipleOf { got, want } => {
+                vec![("got", got.to_string()), ("want", want.to_string())]
+            }
+            Discriminator { prop, got } => {
+                let mut params = vec![("prop", quote(prop))];
+                if let Some(got) = got {
+                    params.push(("got", value(got)));
+                }
+                params
+            }
+            OneOf(Some((i, j))) => vec![("got", list([i, j]))],
+            LimitExceeded { limit } => vec![("limit", limit.to_string())],
+        }
+    }
+
+    /// Returns message of this kind using template from `catalog`.
+    pub fn localize(&self, catalog: &dyn MessageCatalog) -> String {
+        match catalog.template(self) {
+            Some(template) => expand(template, &self.params()),
+            None => self.to_string(),
+        }
+    }
+}
+
+// formats value as in default messages
+fn value(v: &serde_json::Value) -> String {
+    match v {
+        serde_json::Value::String(s) => quote(s),
+        _ => v.to_string(),
+    }
+}
+
+// replaces `{name}` in template with value of param `name`
+pub(crate) fn expand(template: &str, params: &[(&'static str, String)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(i) = rest.find(['{', '}']) {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            out.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+        let param = rest
+            .strip_prefix('{')
+            .and_then(|s| s.find('}').map(|j| &s[..j]))
+            .and_then(|name| params.iter().find(|(n, _)| *n == name));
+        match param {
+            Some((name, value)) => {
+                out.push_str(value);
+                rest = &rest[name.len() + 2..];
+            }
+            None => {
+                out.push_str(&rest[..1]);
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        let params = [("got", "1".to_owned()), ("want", "'a'".to_owned())];
+        let tests = [
+            ("{got} < {want}", "1 < 'a'"),
+            ("{{got}} {got}", "{got} 1"),
+            ("{unknown} {got", "{unknown} {got"),
+            ("}{want}", "}'a'"),
+        ];
+        for (template, want) in tests {
+            assert_eq!(expand(template, &params), want, "template: {template}");
+        }
+    }
+}
@@ -0,0 +1,431 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt::Display,
+};
+
+use serde_json::Value;
+use url::Url;
+
+use crate::{draft::*, util::*, *};
+
+const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+// default value of `jsonSchemaDialect`
+const BASE_DIALECT: &str = "https://spec.openapis.org/oas/3.1/dialect/base";
+
+/// Schemas of an OpenAPI document compiled by [`Compiler::compile_openapi`].
+///
+/// Request and response bodies of operations can be validated
+/// by their `operationId`.
+pub struct OpenApi {
+    schemas: Schemas,
+    components: HashMap<String, SchemaIndex>,
+    operations: HashMap<String, Operation>,
+}
+
+#[derive(Default)]
+struct Operation {
+    request_body: Option<Content>,
+    responses: HashMap<String, Content>, // key is status code, range or DEFAULT
+}
+
+// media type range to schema. `None` if no schema is specified.
+type Content = Vec<(String, Option<SchemaIndex>)>;
+
+impl OpenApi {
+    /// Returns the compiled schemas.
+    pub fn schemas(&self) -> &Schemas {
+        &self.schemas
+    }
+
+    /// Returns the schema at `components/schemas/{name}`.
+    pub fn component(&self, name: &str) -> Option<SchemaIndex> {
+        self.components.get(name).copied()
+    }
+
+    /**
+    Validates request `body` of operation `operation_id`, sent with
+    content-type `media_type`.
+
+    `media_type` is matched against the content of the requestBody
+    exactly, then against `type/*` and `*/*`. Parameters such as
+    `charset` are ignored.
+    */
+    pub fn validate_request<'s, 'v>(
+        &'s self,
+        operation_id: &str,
+        media_type: &str,
+        body: &'v Value,
+    ) -> Result<(), OpenApiError<'s, 'v>> {
+        let op = self.operation(operation_id)?;
+        let Some(content) = &op.request_body else {
+            return Err(OpenApiError::RequestBodyNotFound {
+                operation_id: operation_id.to_owned(),
+            });
+        };
+        self.validate_content(content, media_type, body)
+    }
+
+    /**
+    Validates response `body` of operation `operation_id` with
+    given `status` and content-type `media_type`.
+
+    `status` is matched against status codes of the responses, then
+    against ranges like `2XX` and finally against `default`.
+    `media_type` is matched as in [`OpenApi::validate_request`].
+    */
+    pub fn validate_response<'s, 'v>(
+        &'s self,
+        operation_id: &str,
+        status: u16,
+        media_type: &str,
+        body: &'v Value,
+    ) -> Result<(), OpenApiError<'s, 'v>> {
+        let op = self.operation(operation_id)?;
+        let content = op
+            .responses
+            .get(&status.to_string())
+            .or_else(|| op.responses.get(&format!("{}XX", status / 100)))
+            .or_else(|| op.responses.get("DEFAULT"));
+        let Some(content) = content else {
+            return Err(OpenApiError::ResponseNotFound {
+                operation_id: operation_id.to_owned(),
+                status,
+            });
+        };
+        self.validate_content(content, media_type, body)
+    }
+
+    fn operation<'s, 'v>(
+        &'s self,
+        operation_id: &str,
+    ) -> Result<&'s Operation, OpenApiError<'s, 'v>> {
+        self.operations
+            .get(operation_id)
+            .ok_or_else(|| OpenApiError::OperationNotFound {
+                operation_id: operation_id.to_owned(),
+            })
+    }
+
+    fn validate_content<'s, 'v>(
+        &'s self,
+        content: &Content,
+        media_type: &str,
+        body: &'v Value,
+    ) -> Result<(), OpenApiError<'s, 'v>> {
+        let mt = media_range(media_type);
+        let wildcard = match mt.split_once('/') {
+            Some((t, _)) => format!("{t}/*"),
+            None => "*/*".to_owned(),
+        };
+        let sch = [mt.as_str(), wildcard.as_str(), "*/*"]
+            .into_iter()
+            .find_map(|range| content.iter().find(|(k, _)| k == range));
+        match sch {
+            None => Err(OpenApiError::MediaTypeNotFound {
+                media_type: media_type.to_owned(),
+            }),
+            Some((_, None)) => Ok(()),
+            Some((_, Some(sch))) => self
+                .schemas
+                .validate(body, *sch)
+                .map_err(OpenApiError::ValidationError),
+        }
+    }
+}
+
+// strips parameters and lowercases given media type
+fn media_range(media_type: &str) -> String {
+    let mt = media_type.split(';').next().unwrap_or_default();
+    mt.trim().to_ascii_lowercase()
+}
+
+impl Compiler {
+    /**
+    Compiles schemas in OpenAPI document at `loc`.
+
+    Schemas under `components/schemas` and the schemas of request and
+    response bodies of every operation with `operationId` are compiled.
+    Reference objects are followed, and `$ref` in schemas resolve into
+    the document as usual.
+
+    For OpenAPI 3.1, schemas use the draft named in `jsonSchemaDialect`,
+    which defaults to draft 2020-12. Only standard drafts are supported as
+    dialect. For OpenAPI 3.0, schemas use [`Draft::OpenApi3_0`].
+
+    # Errors
+
+    returns [`CompileError::InvalidOpenApi`] if the document is not
+    OpenAPI 3.0 or 3.1, if its dialect is not supported, if operations
+    share `operationId`, or if reference objects form a cycle.
+
+    [`Draft::OpenApi3_0`]: crate::Draft::OpenApi3_0
+    */
+    pub fn compile_openapi(&mut self, loc: &str) -> Result<OpenApi, CompileError> {
+        let url = UrlFrag::absolute(loc)?.url;
+        let loaded = match self.roots.get(&url) {
+            Some(_) => None,
+            None => Some(self.roots.loader.load(&url)?),
+        };
+        let drafts = match (&loaded, self.roots.get(&url)) {
+            (Some(doc), _) => self.openapi_drafts(&url, doc),
+            (None, Some(root)) => self.openapi_drafts(&url, &root.doc).and_then(|drafts| {
+                // document compiled already, possibly without openapi
+                if root.doc.get("$schema").is_none() && !std::ptr::eq(root.draft, drafts.0) {
+                    return Err(CompileError::InvalidOpenApi {
+                        loc: url.to_string(),
+                        reason: "document is already compiled with other draft".to_owned(),
+                    });
+                }
+                Ok(drafts)
+            }),
+            (None, None) => Err(CompileError::Bug("openapi document not loaded".into())),
+        };
+        let (root_draft, default_draft) = match drafts {
+            Ok(drafts) => drafts,
+            Err(e) => {
+                if let Some(doc) = loaded {
+                    self.roots.loader.restore(&url, doc);
+                }
+                return Err(e);
+            }
+        };
+
+        let prev = std::mem::replace(&mut self.roots.default_draft, root_draft);
+        let result = match loaded {
+            Some(doc) => self.roots.add_loaded(url.clone(), doc),
+            None => Ok(()),
+        };
+        self.roots.default_draft = default_draft;
+        let result = result.and_then(|_| self.compile_openapi_doc(url));
+        self.roots.default_draft = prev;
+        result
+    }
+
+    // returns draft of document root and default draft of other documents,
+    // for openapi document `doc`
+    fn openapi_drafts(
+        &self,
+        url: &Url,
+        doc: &Value,
+    ) -> Result<(&'static crate::draft::Draft, &'static crate::draft::Draft), CompileError> {
+        let invalid = |reason| CompileError::InvalidOpenApi {
+            loc: url.to_string(),
+            reason,
+        };
+        let version = match doc.get("openapi") {
+            Some(Value::String(v)) => v.as_str(),
+            _ => "",
+        };
+        if version.starts_with("3.0.") {
+            return Ok((&OPENAPI30, &OPENAPI30));
+        }
+        if !version.starts_with("3.1.") {
+            return Err(invalid(format!(
+                "unsupported openapi version {}",
+                quote(version)
+            )));
+        }
+        // schemas use `jsonSchemaDialect`, unless document has `$schema`
+        let draft = match doc.get("jsonSchemaDialect") {
+            Some(Value::String(d)) if split(d).0 != BASE_DIALECT => {
+                crate::draft::Draft::from_url(d)
+                    .ok_or_else(|| invalid(format!("unsupported jsonSchemaDialect {}", quote(d))))?
+            }
+            _ => &DRAFT2020,
+        };
+        Ok((draft, self.roots.default_draft))
+    }
+
+    fn compile_openapi_doc(&mut self, url: Url) -> Result<OpenApi, CompileError> {
+        let mut api = OpenApi {
+            schemas: Schemas::new(),
+            components: HashMap::new(),
+            operations: HashMap::new(),
+        };
+        let root = UrlPtr {
+            url,
+            ptr: "".into(),
+        };
+
+        let names = self.openapi_keys(&root, &["components", "schemas"])?;
+        for name in names {
+            let up = UrlPtr {
+                url: root.url.clone(),
+                ptr: root.ptr.append2("components", "schemas").append(&name),
+            };
+            let sch = self.compile(&up.to_string(), &mut api.schemas)?;
+            api.components.insert(name, sch);
+        }
+
+        for path in self.openapi_keys(&root, &["paths"])? {
+            let item = self.openapi_deref(UrlPtr {
+                url: root.url.clone(),
+                ptr: root.ptr.append2("paths", &path),
+            })?;
+            for method in METHODS {
+                let Some(Value::String(id)) = self
+                    .openapi_value(&item, &[method, "operationId"])?
+                    .cloned()
+                else {
+                    continue;
+                };
+                let op_up = UrlPtr {
+                    url: item.url.clone(),
+                    ptr: item.ptr.append(method),
+                };
+                if api.operations.contains_key(&id) {
+                    return Err(CompileError::InvalidOpenApi {
+                        loc: op_up.to_string(),
+                        reason: format!("duplicate operationId {}", quote(&id)),
+                    });
+                }
+                let mut op = Operation::default();
+                if self.openapi_value(&op_up, &["requestBody"])?.is_some() {
+                    let up = self.openapi_deref(UrlPtr {
+                        url: op_up.url.clone(),
+                        ptr: op_up.ptr.append("requestBody"),
+                    })?;
+                    op.request_body = Some(self.compile_content(up, &mut api.schemas)?);
+                }
+                for status in self.openapi_keys(&op_up, &["responses"])? {
+                    let up = self.openapi_deref(UrlPtr {
+                        url: op_up.url.clone(),
+                        ptr: op_up.ptr.append2("responses", &status),
+                    })?;
+                    let content = self.compile_content(up, &mut api.schemas)?;
+                    op.responses.insert(status.to_ascii_uppercase(), content);
+                }
+                api.operations.insert(id, op);
+            }
+        }
+
+        Ok(api)
+    }
+
+    // compiles schemas in `content` of request body or response at `up`
+    fn compile_content(
+        &mut self,
+        up: UrlPtr,
+        target: &mut Schemas,
+    ) -> Result<Content, CompileError> {
+        let mut content = vec![];
+        for mt in self.openapi_keys(&up, &["content"])? {
+            let has_schema = self
+                .openapi_value(&up, &["content", &mt, "schema"])?
+                .is_some();
+            let sch = if has_schema {
+                let sch_up = UrlPtr {
+                    url: up.url.clone(),
+                    ptr: up.ptr.append2("content", &mt).append("schema"),
+                };
+                Some(self.compile(&sch_up.to_string(), target)?)
+            } else {
+                None
+            };
+            content.push((media_range(&mt), sch));
+        }
+        Ok(content)
+    }
+
+    // follows reference objects starting at `up`
+    fn openapi_deref(&mut self, mut up: UrlPtr) -> Result<UrlPtr, CompileError> {
+        let mut visited = HashSet::new();
+        loop {
+            let Some(Value::String(ref_)) = self.openapi_value(&up, &["$ref"])?.cloned() else {
+                return Ok(up);
+            };
+            if !visited.insert(up.clone()) {
+                return Err(CompileError::InvalidOpenApi {
+                    loc: up.to_string(),
+                    reason: "reference cycle".to_owned(),
+                });
+            }
+            let uf = UrlFrag::join(&up.url, &ref_)?;
+            up = self.roots.resolve_fragment(uf)?;
+        }
+    }
+
+    // returns value at `path` relative to `up`
+    fn openapi_value(
+        &mut self,
+        up: &UrlPtr,
+        path: &[&str],
+    ) -> Result<Option<&Value>, CompileError> {
+        self.roots.or_load(up.url.clone())?;
+        let Some(root) = self.roots.get(&up.url) else {
+            return Err(CompileError::Bug("or_load didn't add".into()));
+        };
+        let mut v = up.lookup(&root.doc)?;
+        for tok in path {
+            match v.get(tok) {
+                Some(pvalue) => v = pvalue,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(v))
+    }
+
+    // returns keys of object at `path` relative to `up`
+    fn openapi_keys(&mut self, up: &UrlPtr, path: &[&str]) -> Result<Vec<String>, CompileError> {
+        match self.openapi_value(up, path)? {
+            Some(Value::Object(obj)) => Ok(obj.keys().cloned().collect()),
+            _ => Ok(vec![]),
+        }
+    }
+}
+
+/// Error type for [`OpenApi`] validation failures.
+#[derive(Debug)]
+pub enum OpenApiError<'s, 'v> {
+    /// No operation with `operationId` exists.
+    OperationNotFound { operation_id: String },
+
+    /// Operation has no requestBody.
+    RequestBodyNotFound { operation_id: String },
+
+    /// Operation has no response for `status`.
+    ResponseNotFound { operation_id: String, status: u16 },
+
+    /// `media_type` is not listed in content of requestBody or response.
+    MediaTypeNotFound { media_type: String },
+
+    /// Body is not valid against its schema.
+    ValidationError(ValidationError<'s, 'v>),
+}
+
+impl<'s, 'v> Error for OpenApiError<'s, 'v> {}
+
+impl<'s, 'v> Display for OpenApiError<'s, 'v> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OperationNotFound { operation_id } => {
+                write!(f, "operation {} not found", quote(operation_id))
+            }
+            Self::RequestBodyNotFound { operation_id } => {
+                write!(f, "operation {} has no requestBody", quote(operation_id))
+            }
+            Self::ResponseNotFound {
+                operation_id,
+                status,
+            } => write!(
+                f,
+                "operation {} has no response for status {status}",
+                quote(operation_id)
+            ),
+            Self::MediaTypeNotFound { media_type } => {
+                write!(f, "media type {} not supported", quote(media_type))
+            }
+            Self::ValidationError(e) => {
+                if f.alternate() {
+                    write!(f, "{e:#}")
+                } else {
+                    write!(f, "{e}")
+                }
+            }
+        }
+    }
+}
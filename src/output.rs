@@ -8,7 +8,9 @@ use serde::{
     Serialize,
 };
 
-use crate::{util::*, ErrorKind, InstanceLocation, ValidationError};
+use crate::{
+    util::*, ErrorKind, InstanceLocation, MessageCatalog, Position, SourceMap, ValidationError,
+};
 
 impl<'s, 'v> ValidationError<'s, 'v> {
     fn absolute_keyword_location(&self) -> AbsoluteKeywordLocation<'s> {
@@ -35,7 +37,11 @@ impl<'s, 'v> ValidationError<'s, 'v> {
     }
 
     /// The `Basic` structure, a flat list of output units.
-    pub fn basic_output(&self) -> OutputUnit {
+    pub fn basic_output(&self) -> OutputUnit<'_, 's, 'v> {
+        self.basic_output_with(ReportOpts::default())
+    }
+
+    fn basic_output_with<'e>(&'e self, opts: ReportOpts<'e>) -> OutputUnit<'e, 's, 'v> {
         let mut outputs = vec![];
 
         let mut in_ref = InRef::default();
@@ -58,7 +64,10 @@ impl<'s, 'v> ValidationError<'s, 'v> {
                         keyword_location: kw_loc.get(e),
                         absolute_keyword_location,
                         instance_location: &e.instance_location,
+                        instance_position: opts.position(&e.instance_location),
                         error: OutputError::Leaf(&e.kind),
+                        message: e.message.as_deref(),
+                        catalog: opts.catalog,
                     });
                 }
                 DfsItem::Post(e) => {
@@ -81,12 +90,19 @@ impl<'s, 'v> ValidationError<'s, 'v> {
             keyword_location: String::new(),
             absolute_keyword_location: None,
             instance_location: &self.instance_location,
+            instance_position: opts.position(&self.instance_location),
             error,
+            message: self.message.as_deref(),
+            catalog: opts.catalog,
         }
     }
 
     /// The `Detailed` structure, based on the schema.
-    pub fn detailed_output(&self) -> OutputUnit {
+    pub fn detailed_output(&self) -> OutputUnit<'_, 's, 'v> {
+        self.detailed_output_with(ReportOpts::default())
+    }
+
+    fn detailed_output_with<'e>(&'e self, opts: ReportOpts<'e>) -> OutputUnit<'e, 's, 'v> {
         let mut root = None;
         let mut stack: Vec<OutputUnit> = vec![];
 
@@ -110,7 +126,10 @@ impl<'s, 'v> ValidationError<'s, 'v> {
                         keyword_location: kw_loc.get(e),
                         absolute_keyword_location,
                         instance_location: &e.instance_location,
+                        instance_position: opts.position(&e.instance_location),
                         error: OutputError::Leaf(&e.kind),
+                        message: e.message.as_deref(),
+                        catalog: opts.catalog,
                     });
                 }
                 DfsItem::Post(e) => {
@@ -135,6 +154,205 @@ impl<'s, 'v> ValidationError<'s, 'v> {
         }
         root.unwrap()
     }
+
+    /// The `List` output format of draft-next, a flat list of output nodes.
+    pub fn list_output(&self) -> OutputNode<'_, 's, 'v> {
+        self.list_output_with(ReportOpts::default())
+    }
+
+    fn list_output_with<'e>(&'e self, opts: ReportOpts<'e>) -> OutputNode<'e, 's, 'v> {
+        fn flatten<'e, 's, 'v>(
+            mut node: OutputNode<'e, 's, 'v>,
+            list: &mut Vec<OutputNode<'e, 's, 'v>>,
+        ) {
+            let details = std::mem::take(&mut node.details);
+            if !node.errors.is_empty() {
+                list.push(node);
+            }
+            for node in details {
+                flatten(node, list);
+            }
+        }
+
+        let mut root = self.hierarchical_output_with(opts);
+        let mut list = vec![];
+        for node in std::mem::take(&mut root.details) {
+            flatten(node, &mut list);
+        }
+        if !root.errors.is_empty() {
+            list.insert(0, root.clone_shallow());
+            root.errors.clear();
+            root.messages.clear();
+        }
+        root.details = list;
+        root
+    }
+
+    /// The `Hierarchical` output format of draft-next, based on the schema.
+    pub fn hierarchical_output(&self) -> OutputNode<'_, 's, 'v> {
+        self.hierarchical_output_with(ReportOpts::default())
+    }
+
+    fn hierarchical_output_with<'e>(&'e self, opts: ReportOpts<'e>) -> OutputNode<'e, 's, 'v> {
+        let mut root = None;
+        let mut stack: Vec<OutputNode> = vec![];
+
+        let mut kw_loc = KeywordLocation::default();
+        for node in DfsIterator::new(self) {
+            match node {
+                DfsItem::Pre(e) => {
+                    kw_loc.pre(e);
+                    if e.skip() {
+                        continue;
+                    }
+                    let schema_location = match &e.kind {
+                        ErrorKind::Reference { url, .. } => url,
+                        _ => e.schema_url,
+                    };
+                    let mut errors = vec![];
+                    let mut messages = vec![];
+                    match e.kind.keyword_path() {
+                        Some(kw_path) => errors.push((kw_path.keyword, &e.kind)),
+                        None if e.message.is_some() => errors.push(("errorMessage", &e.kind)),
+                        None => {}
+                    }
+                    if !errors.is_empty() {
+                        messages.push(e.message.as_deref());
+                    }
+                    stack.push(OutputNode {
+                        valid: false,
+                        evaluation_path: kw_loc.loc.clone(),
+                        schema_location,
+                        instance_location: &e.instance_location,
+                        instance_position: opts.position(&e.instance_location),
+                        errors,
+                        details: vec![],
+                        messages,
+                        catalog: opts.catalog,
+                    });
+                }
+                DfsItem::Post(e) => {
+                    kw_loc.post();
+                    if e.skip() {
+                        continue;
+                    }
+                    let node = stack.pop().unwrap();
+                    if let Some(parent) = stack.last_mut() {
+                        let same = parent.evaluation_path == node.evaluation_path
+                            && parent.instance_location.to_string()
+                                == node.instance_location.to_string();
+                        if same {
+                            // errors from same subschema and instance
+                            parent.errors.extend(node.errors);
+                            parent.messages.extend(node.messages);
+                            parent.details.extend(node.details);
+                        } else {
+                            parent.details.push(node);
+                        }
+                    } else {
+                        root.replace(node);
+                    }
+                }
+            }
+        }
+        root.unwrap()
+    }
+}
+
+// ErrorReport --
+
+/**
+[`ValidationError`] with messages taken from a [`MessageCatalog`]
+and positions taken from a [`SourceMap`].
+
+Returned by [`ValidationError::localize`] and [`ValidationError::with_source_map`].
+It can be displayed and converted into output formats like [`ValidationError`].
+*/
+#[derive(Clone, Copy)]
+pub struct ErrorReport<'e, 's, 'v> {
+    error: &'e ValidationError<'s, 'v>,
+    opts: ReportOpts<'e>,
+}
+
+/// Former name of [`ErrorReport`], which now also carries positions.
+#[deprecated(note = "use `ErrorReport`")]
+pub type LocalizedError<'e, 's, 'v> = ErrorReport<'e, 's, 'v>;
+
+#[derive(Clone, Copy, Default)]
+struct ReportOpts<'e> {
+    catalog: Option<&'e dyn MessageCatalog>,
+    source_map: Option<&'e SourceMap>,
+}
+
+impl<'e> ReportOpts<'e> {
+    fn position(&self, loc: &InstanceLocation) -> Option<Position> {
+        self.source_map.and_then(|m| m.position(loc))
+    }
+}
+
+impl<'s, 'v> ValidationError<'s, 'v> {
+    fn report<'e>(&'e self) -> ErrorReport<'e, 's, 'v> {
+        ErrorReport {
+            error: self,
+            opts: ReportOpts::default(),
+        }
+    }
+
+    /// Returns this error with messages taken from `catalog`.
+    pub fn localize<'e>(&'e self, catalog: &'e dyn MessageCatalog) -> ErrorReport<'e, 's, 'v> {
+        self.report().localize(catalog)
+    }
+
+    /// Returns this error with positions of instance locations taken from `source_map`.
+    pub fn with_source_map<'e>(&'e self, source_map: &'e SourceMap) -> ErrorReport<'e, 's, 'v> {
+        self.report().with_source_map(source_map)
+    }
+}
+
+impl<'e, 's, 'v> ErrorReport<'e, 's, 'v> {
+    /// Takes messages from `catalog`.
+    pub fn localize(mut self, catalog: &'e dyn MessageCatalog) -> Self {
+        self.opts.catalog = Some(catalog);
+        self
+    }
+
+    /// Takes positions of instance locations from `source_map`.
+    pub fn with_source_map(mut self, source_map: &'e SourceMap) -> Self {
+        self.opts.source_map = Some(source_map);
+        self
+    }
+
+    /// Same as [`ValidationError::flag_output`].
+    pub fn flag_output(&self) -> FlagOutput {
+        self.error.flag_output()
+    }
+
+    /// Same as [`ValidationError::basic_output`].
+    pub fn basic_output(&self) -> OutputUnit<'e, 's, 'v> {
+        self.error.basic_output_with(self.opts)
+    }
+
+    /// Same as [`ValidationError::detailed_output`].
+    pub fn detailed_output(&self) -> OutputUnit<'e, 's, 'v> {
+        self.error.detailed_output_with(self.opts)
+    }
+
+    /// Same as [`ValidationError::list_output`].
+    pub fn list_output(&self) -> OutputNode<'e, 's, 'v> {
+        self.error.list_output_with(self.opts)
+    }
+
+    /// Same as [`ValidationError::hierarchical_output`].
+    pub fn hierarchical_output(&self) -> OutputNode<'e, 's, 'v> {
+        self.error.hierarchical_output_with(self.opts)
+    }
+}
+
+impl<'e, 's, 'v> Display for ErrorReport<'e, 's, 'v> {
+    /// Formats error hierarchy. Use `#` to show the schema location.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.error.write(f, self.opts)
+    }
 }
 
 // DfsIterator --
@@ -142,6 +360,12 @@ impl<'s, 'v> ValidationError<'s, 'v> {
 impl<'s, 'v> Display for ValidationError<'s, 'v> {
     /// Formats error hierarchy. Use `#` to show the schema location.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write(f, ReportOpts::default())
+    }
+}
+
+impl<'s, 'v> ValidationError<'s, 'v> {
+    fn write(&self, f: &mut std::fmt::Formatter<'_>, opts: ReportOpts) -> std::fmt::Result {
         let mut indent = Indent::default();
         let mut sloc = SchemaLocation::default();
         // let mut kw_loc = KeywordLocation::default();
@@ -157,15 +381,22 @@ impl<'s, 'v> Display for ValidationError<'s, 'v> {
                         sloc.pre(e);
                     }
                     if let ErrorKind::Schema { .. } = &e.kind {
-                        write!(f, "jsonschema {}", e.kind)?;
+                        write!(f, "jsonschema {}", message(&e.kind, None, opts.catalog))?;
                     } else {
                         write!(f, "at {}", quote(&e.instance_location.to_string()))?;
+                        if let Some(pos) = opts.position(&e.instance_location) {
+                            write!(f, " ({pos})")?;
+                        }
                         if f.alternate() {
                             write!(f, " [{}]", sloc)?;
                             // write!(f, " [{}]", kw_loc.get(e))?;
                             // write!(f, " [{}]", e.absolute_keyword_location())?;
                         }
-                        write!(f, ": {}", e.kind)?;
+                        write!(
+                            f,
+                            ": {}",
+                            message(&e.kind, e.message.as_deref(), opts.catalog)
+                        )?;
                     }
                 }
                 DfsItem::Post(e) => {
@@ -420,7 +651,11 @@ pub struct OutputUnit<'e, 's, 'v> {
     pub absolute_keyword_location: Option<AbsoluteKeywordLocation<'s>>,
     /// The location of the JSON value within the instance being validated
     pub instance_location: &'e InstanceLocation<'v>,
+    /// The position of `instance_location` in the source text, if known
+    pub instance_position: Option<Position>,
     pub error: OutputError<'e, 's, 'v>,
+    message: Option<&'e str>,
+    catalog: Option<&'e dyn MessageCatalog>,
 }
 
 impl<'e, 's, 'v> Serialize for OutputUnit<'e, 's, 'v> {
@@ -428,7 +663,9 @@ impl<'e, 's, 'v> Serialize for OutputUnit<'e, 's, 'v> {
     where
         S: serde::Serializer,
     {
-        let n = 4 + self.absolute_keyword_location.as_ref().map_or(0, |_| 1);
+        let n = 4
+            + self.absolute_keyword_location.as_ref().map_or(0, |_| 1)
+            + self.instance_position.as_ref().map_or(0, |_| 1);
         let mut map = serializer.serialize_map(Some(n))?;
         map.serialize_entry("valid", &self.valid)?;
         map.serialize_entry("keywordLocation", &self.keyword_location.to_string())?;
@@ -436,11 +673,15 @@ impl<'e, 's, 'v> Serialize for OutputUnit<'e, 's, 'v> {
             map.serialize_entry("absoluteKeywordLocation", &s.to_string())?;
         }
         map.serialize_entry("instanceLocation", &self.instance_location.to_string())?;
-        let pname = match self.error {
-            OutputError::Leaf(_) => "error",
-            OutputError::Branch(_) => "errors",
-        };
-        map.serialize_entry(pname, &self.error)?;
+        if let Some(pos) = &self.instance_position {
+            map.serialize_entry("instancePosition", pos)?;
+        }
+        match &self.error {
+            OutputError::Leaf(kind) => {
+                map.serialize_entry("error", &message(kind, self.message, self.catalog))?
+            }
+            OutputError::Branch(_) => map.serialize_entry("errors", &self.error)?,
+        }
         map.end()
     }
 }
@@ -477,12 +718,111 @@ impl<'e, 's, 'v> Serialize for OutputError<'e, 's, 'v> {
     }
 }
 
+/// Single node used in List/Hierarchical output formats of draft-next.
+pub struct OutputNode<'e, 's, 'v> {
+    pub valid: bool,
+    /// The relative location of the validating subschema, following references
+    pub evaluation_path: String,
+    /// The absolute, dereferenced location of the validating subschema
+    pub schema_location: &'s str,
+    /// The location of the JSON value within the instance being validated
+    pub instance_location: &'e InstanceLocation<'v>,
+    /// The position of `instance_location` in the source text, if known
+    pub instance_position: Option<Position>,
+    /// Errors produced by keywords of the subschema
+    pub errors: Vec<(&'static str, &'e ErrorKind<'s, 'v>)>,
+    /// Nested nodes.
+    pub details: Vec<OutputNode<'e, 's, 'v>>,
+    messages: Vec<Option<&'e str>>, // from `errorMessage`, same order as `errors`
+    catalog: Option<&'e dyn MessageCatalog>,
+}
+
+impl<'e, 's, 'v> OutputNode<'e, 's, 'v> {
+    fn clone_shallow(&self) -> Self {
+        Self {
+            valid: self.valid,
+            evaluation_path: self.evaluation_path.clone(),
+            schema_location: self.schema_location,
+            instance_location: self.instance_location,
+            instance_position: self.instance_position,
+            errors: self.errors.clone(),
+            details: vec![],
+            messages: self.messages.clone(),
+            catalog: self.catalog,
+        }
+    }
+}
+
+impl<'e, 's, 'v> Serialize for OutputNode<'e, 's, 'v> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("valid", &self.valid)?;
+        map.serialize_entry("evaluationPath", &self.evaluation_path)?;
+        map.serialize_entry("schemaLocation", self.schema_location)?;
+        map.serialize_entry("instanceLocation", &self.instance_location.to_string())?;
+        if let Some(pos) = &self.instance_position {
+            map.serialize_entry("instancePosition", pos)?;
+        }
+        if !self.errors.is_empty() {
+            // messages of same keyword are joined, to keep keys unique
+            let mut errors: Vec<(&str, String)> = vec![];
+            for ((kw, kind), msg) in self.errors.iter().zip(&self.messages) {
+                let msg = message(kind, *msg, self.catalog);
+                match errors.iter_mut().find(|(k, _)| k == kw) {
+                    Some((_, m)) => {
+                        m.push_str("; ");
+                        m.push_str(&msg);
+                    }
+                    None => errors.push((kw, msg)),
+                }
+            }
+            map.serialize_entry("errors", &ErrorsMap(&errors))?;
+        }
+        if !self.details.is_empty() {
+            map.serialize_entry("details", &self.details)?;
+        }
+        map.end()
+    }
+}
+
+impl<'e, 's, 'v> Display for OutputNode<'e, 's, 'v> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write_json_to_fmt(f, self)
+    }
+}
+
+struct ErrorsMap<'a>(&'a [(&'static str, String)]);
+
+impl<'a> Serialize for ErrorsMap<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (kw, msg) in self.0 {
+            map.serialize_entry(kw, msg)?;
+        }
+        map.end()
+    }
+}
+
 // AbsoluteKeywordLocation --
 
 impl<'s, 'v> ErrorKind<'s, 'v> {
+    // keyword in schema, which differs from keyword_path for additionalProperties
+    pub(crate) fn schema_keyword(&self) -> Option<&'static str> {
+        match self {
+            ErrorKind::AdditionalProperties { .. } => Some("additionalProperties"),
+            _ => Some(self.keyword_path()?.keyword),
+        }
+    }
+
     pub fn keyword_path(&self) -> Option<KeywordPath<'s>> {
         #[inline(always)]
-        fn kw(kw: &'static str) -> Option<KeywordPath> {
+        fn kw<'s>(kw: &'static str) -> Option<KeywordPath<'s>> {
             Some(KeywordPath {
                 keyword: kw,
                 token: None,
@@ -522,6 +862,7 @@ impl<'s, 'v> ErrorKind<'s, 'v> {
             MinContains { .. } => kw("minContains"),
             MaxContains { .. } => kw("maxContains"),
             UniqueItems { .. } => kw("uniqueItems"),
+            ListMapKeys { .. } => kw("x-kubernetes-list-map-keys"),
             AdditionalItems { .. } => kw("additionalItems"),
             MinLength { .. } => kw("minLength"),
             MaxLength { .. } => kw("maxLength"),
@@ -534,9 +875,13 @@ impl<'s, 'v> ErrorKind<'s, 'v> {
             ExclusiveMaximum { .. } => kw("exclusiveMaximum"),
             MultipleOf { .. } => kw("multipleOf"),
             Not => kw("not"),
+            Disallow => kw("disallow"),
+            Discriminator { .. } => kw("discriminator"),
             AllOf => kw("allOf"),
             AnyOf => kw("anyOf"),
             OneOf(_) => kw("oneOf"),
+            LimitExceeded { .. } => None,
+            Cancelled => None,
         }
     }
 }
@@ -608,6 +953,15 @@ impl<'s> Display for SchemaToken<'s> {
 
 // helpers --
 
+// `custom` is message from `errorMessage`
+fn message(kind: &ErrorKind, custom: Option<&str>, catalog: Option<&dyn MessageCatalog>) -> String {
+    match (custom, catalog) {
+        (Some(msg), _) => msg.to_owned(),
+        (None, Some(catalog)) => kind.localize(catalog),
+        (None, None) => kind.to_string(),
+    }
+}
+
 fn write_json_to_fmt<T>(f: &mut std::fmt::Formatter, value: &T) -> Result<(), std::fmt::Error>
 where
     T: ?Sized + Serialize,
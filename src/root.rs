@@ -11,6 +11,7 @@ pub(crate) struct Root {
     pub(crate) url: Url,
     pub(crate) doc: Value,
     pub(crate) meta_vocabs: Option<Vec<String>>,
+    pub(crate) regexes: usize, // number of regexes in compiled schemas
 }
 
 impl Root {
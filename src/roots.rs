@@ -6,6 +6,7 @@ use crate::{
     loader::DefaultUrlLoader,
     root::Root,
     util::*,
+    ValidationError,
 };
 
 use serde_json::Value;
@@ -65,59 +66,36 @@ impl Roots {
         debug_assert!(url.fragment().is_none(), "trying to add root with fragment");
         if !self.map.contains_key(&url) {
             let doc = self.loader.load(&url)?;
-            Roots::add_root(
-                self.default_draft,
-                &HashMap::new(),
-                &mut self.map,
-                &self.loader,
-                HashSet::new(),
-                url,
-                doc,
-            )?;
+            self.add_loaded(url, doc)?;
         }
         Ok(())
     }
 
+    // adds root for `doc` already loaded from `url`
+    pub(crate) fn add_loaded(&mut self, url: Url, doc: Value) -> Result<(), CompileError> {
+        Roots::add_root(
+            self.default_draft,
+            &HashMap::new(),
+            &mut self.map,
+            &self.loader,
+            HashSet::new(),
+            url,
+            doc,
+        )?;
+        Ok(())
+    }
+
     fn add_root<'a>(
         default_draft: &'static Draft,
         rmap: &HashMap<Url, Root>,
         wmap: &'a mut HashMap<Url, Root>,
         loader: &DefaultUrlLoader,
-        mut cycle: HashSet<Url>,
+        cycle: HashSet<Url>,
         url: Url,
         doc: Value,
     ) -> Result<&'a Root, CompileError> {
-        let (draft, vocabs) = (|| {
-            let Value::Object(obj) = &doc else {
-                return Ok((default_draft, None));
-            };
-            let Some(Value::String(sch)) = obj.get("$schema") else {
-                return Ok((default_draft, None));
-            };
-            if let Some(draft) = Draft::from_url(sch) {
-                return Ok((draft, None));
-            }
-            let (sch, _) = split(sch);
-            let sch = Url::parse(sch).map_err(|e| InvalidMetaSchemaUrl {
-                url: url.as_str().to_owned(),
-                src: e.into(),
-            })?;
-            if let Some(r) = rmap.get(&sch) {
-                return Ok((r.draft, r.get_reqd_vocabs()?));
-            }
-            if let Some(r) = wmap.get(&sch) {
-                return Ok((r.draft, r.get_reqd_vocabs()?));
-            }
-            if sch == url {
-                return Err(UnsupportedDraft { url: sch.into() });
-            }
-            if !cycle.insert(sch.clone()) {
-                return Err(MetaSchemaCycle { url: sch.into() });
-            }
-            let doc = loader.load(&sch)?;
-            let meta_root = Roots::add_root(default_draft, rmap, wmap, loader, cycle, sch, doc)?;
-            Ok((meta_root.draft, meta_root.get_reqd_vocabs()?))
-        })()?;
+        let (draft, vocabs) =
+            Roots::detect_draft(default_draft, rmap, wmap, loader, cycle, &url, &doc)?;
 
         let resources = {
             let mut m = HashMap::default();
@@ -141,10 +119,76 @@ impl Roots {
             url: url.clone(),
             doc,
             meta_vocabs: vocabs,
+            regexes: 0,
         };
         Ok(wmap.entry(url).or_insert(r))
     }
 
+    // returns draft and required vocabularies of `doc`, loading its metaschema if needed
+    fn detect_draft(
+        default_draft: &'static Draft,
+        rmap: &HashMap<Url, Root>,
+        wmap: &mut HashMap<Url, Root>,
+        loader: &DefaultUrlLoader,
+        mut cycle: HashSet<Url>,
+        url: &Url,
+        doc: &Value,
+    ) -> Result<(&'static Draft, Option<Vec<String>>), CompileError> {
+        let Value::Object(obj) = doc else {
+            return Ok((default_draft, None));
+        };
+        let Some(Value::String(sch)) = obj.get("$schema") else {
+            return Ok((default_draft, None));
+        };
+        if let Some(draft) = Draft::from_url(sch) {
+            return Ok((draft, None));
+        }
+        let (sch, _) = split(sch);
+        let sch = Url::parse(sch).map_err(|e| InvalidMetaSchemaUrl {
+            url: url.as_str().to_owned(),
+            src: e.into(),
+        })?;
+        if let Some(r) = rmap.get(&sch) {
+            return Ok((r.draft, r.get_reqd_vocabs()?));
+        }
+        if let Some(r) = wmap.get(&sch) {
+            return Ok((r.draft, r.get_reqd_vocabs()?));
+        }
+        if sch == *url {
+            return Err(UnsupportedDraft { url: sch.into() });
+        }
+        if !cycle.insert(sch.clone()) {
+            return Err(MetaSchemaCycle { url: sch.into() });
+        }
+        let doc = loader.load(&sch)?;
+        let meta_root = Roots::add_root(default_draft, rmap, wmap, loader, cycle, sch, doc)?;
+        Ok((meta_root.draft, meta_root.get_reqd_vocabs()?))
+    }
+
+    /// Validates document at `url` against its metaschema, without
+    /// collecting its resources.
+    pub(crate) fn validate(
+        &mut self,
+        url: &Url,
+    ) -> Result<Result<(), ValidationError<'static, 'static>>, CompileError> {
+        if let Some(root) = self.map.get(url) {
+            return root.draft.validate_value(&root.doc);
+        }
+        let doc = self.loader.load(url)?;
+        let draft = Roots::detect_draft(
+            self.default_draft,
+            &HashMap::new(),
+            &mut self.map,
+            &self.loader,
+            HashSet::new(),
+            url,
+            &doc,
+        );
+        let result = draft.and_then(|(draft, _)| draft.validate_value(&doc));
+        self.loader.restore(url, doc);
+        result
+    }
+
     pub(crate) fn enqueue_root<'a>(
         &self,
         url: Url,
@@ -162,6 +206,48 @@ impl Roots {
         )
     }
 
+    /// Unloads roots whose url does not satisfy `keep`.
+    pub(crate) fn retain(&mut self, keep: impl Fn(&Url) -> bool) {
+        let urls: Vec<Url> = self.map.keys().filter(|url| !keep(url)).cloned().collect();
+        for url in urls {
+            if let Some(root) = self.map.remove(&url) {
+                self.loader.unload(&url, root.doc);
+            }
+        }
+    }
+
+    /// Forgets documents loaded from `url`, so that they are loaded again.
+    /// If `url` has no query, all documents of multi-document file are
+    /// forgotten. Returns urls of forgotten documents.
+    pub(crate) fn invalidate(&mut self, url: &Url) -> Result<Vec<Url>, CompileError> {
+        let matches = |u: &Url| {
+            if u == url {
+                return true;
+            }
+            let mut u = u.clone();
+            u.set_query(None);
+            url.query().is_none() && u == *url
+        };
+        let mut urls: Vec<Url> = self.map.keys().filter(|u| matches(u)).cloned().collect();
+        self.retain(|u| !matches(u));
+        if !urls.contains(url) {
+            urls.push(url.clone());
+        }
+        for url in &urls {
+            self.loader.uncache(url)?;
+        }
+        Ok(urls)
+    }
+
+    /// Adds number of regexes in newly compiled schemas of each document.
+    pub(crate) fn add_regexes(&mut self, regexes: HashMap<Url, usize>) {
+        for (url, n) in regexes {
+            if let Some(root) = self.map.get_mut(&url) {
+                root.regexes += n;
+            }
+        }
+    }
+
     pub(crate) fn insert(&mut self, roots: &mut HashMap<Url, Root>) {
         self.map.extend(roots.drain());
     }
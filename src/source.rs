@@ -0,0 +1,849 @@
+use std::{collections::HashMap, error::Error, fmt::Display, sync::Arc};
+
+use once_cell::sync::OnceCell;
+
+use serde::{ser::SerializeMap, Serialize};
+use serde_json::Value;
+
+use crate::{util::*, InstanceLocation};
+
+/// Line and column in source text, both starting from 1.
+///
+/// Column is counted in characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+impl Serialize for Position {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("line", &self.line)?;
+        map.serialize_entry("column", &self.column)?;
+        map.end()
+    }
+}
+
+/// Format of source text of a document.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SourceFormat {
+    Json,
+    /// JSON with comments and trailing commas, as used by VS Code.
+    Jsonc,
+    /// Requires feature `json5`.
+    Json5,
+    /// Requires feature `toml`.
+    Toml,
+    /// Requires feature `yaml`.
+    Yaml,
+}
+
+impl SourceFormat {
+    /// Returns format for file extension `ext`, which is one of
+    /// `json`, `jsonc`, `json5`, `toml`, `yaml` or `yml`.
+    pub fn from_extension(ext: &str) -> Option<SourceFormat> {
+        match ext {
+            "json" => Some(Self::Json),
+            "jsonc" => Some(Self::Jsonc),
+            "json5" => Some(Self::Json5),
+            "toml" => Some(Self::Toml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+
+    /**
+    Returns format for `media_type`, ignoring its parameters.
+
+    Recognizes `json`, `yaml`, `x-yaml`, `toml`, `json5` subtypes and
+    structured syntax suffixes like `application/schema+json`.
+    */
+    pub fn from_media_type(media_type: &str) -> Option<SourceFormat> {
+        let (essence, _) = media_type.split_once(';').unwrap_or((media_type, ""));
+        let (_, subtype) = essence.trim().split_once('/')?;
+        let subtype = subtype.to_ascii_lowercase();
+        let (_, suffix) = subtype.rsplit_once('+').unwrap_or(("", &subtype));
+        match suffix {
+            "json" => Some(Self::Json),
+            "jsonc" => Some(Self::Jsonc),
+            "json5" => Some(Self::Json5),
+            "toml" => Some(Self::Toml),
+            "yaml" | "x-yaml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+
+    // returns format for extension of last segment in url `path`
+    pub(crate) fn from_path(path: &str) -> Option<SourceFormat> {
+        let (_, name) = path.rsplit_once('/').unwrap_or(("", path));
+        let (_, ext) = name.rsplit_once('.')?;
+        Self::from_extension(ext)
+    }
+}
+
+/// Error in parsing source text of a document.
+#[derive(Debug)]
+pub struct ParseError {
+    pub message: String,
+    /// Position in source text where error is detected, if known.
+    pub position: Option<Position>,
+}
+
+impl ParseError {
+    fn new(message: impl Into<String>, position: Option<Position>) -> Self {
+        Self {
+            message: message.into(),
+            position,
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.position {
+            Some(pos) => write!(
+                f,
+                "{} at line {} column {}",
+                self.message, pos.line, pos.column
+            ),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl From<serde_json::Error> for ParseError {
+    fn from(e: serde_json::Error) -> Self {
+        let message = e.to_string();
+        let suffix = format!(" at line {} column {}", e.line(), e.column());
+        let message = message.strip_suffix(&suffix).unwrap_or(&message);
+        let position = (e.line() > 0).then_some(Position {
+            line: e.line(),
+            column: e.column(),
+        });
+        Self::new(message, position)
+    }
+}
+
+/**
+Positions of values in the source text of a document.
+
+Use [`SourceMap::parse_json`] to parse a document along with its
+source map, then pass the source map to [`ValidationError::with_source_map`]
+to report the positions of errors.
+
+```
+# use boon::*;
+# use serde_json::json;
+let mut schemas = Schemas::new();
+let mut compiler = Compiler::new();
+compiler.add_resource("schema.json", json!({"items": {"type": "string"}})).unwrap();
+let sch = compiler.compile("schema.json", &mut schemas).unwrap();
+
+let (instance, source_map) = SourceMap::parse_json("[\n  \"a\",\n  1\n]").unwrap();
+let err = schemas.validate(&instance, sch).unwrap_err();
+let pos = source_map.position(&err.causes[0].instance_location);
+assert_eq!(pos, Some(Position { line: 3, column: 3 }));
+assert!(err.with_source_map(&source_map).to_string().contains("at '/1' (3:3)"));
+```
+
+[`ValidationError::with_source_map`]: crate::ValidationError::with_source_map
+*/
+#[derive(Debug, Default, Clone)]
+pub struct SourceMap {
+    positions: HashMap<String, Position>, // json-pointer to position of value
+}
+
+impl SourceMap {
+    /// Parses json `text`, recording the position of every value.
+    pub fn parse_json(text: &str) -> Result<(Value, SourceMap), serde_json::Error> {
+        let value = serde_json::from_str(text)?;
+        let mut scanner = JsonScanner {
+            text: text.as_bytes(),
+            offset: 0,
+            pos: Position { line: 1, column: 1 },
+            map: SourceMap::default(),
+        };
+        scanner.value(&mut String::new());
+        Ok((value, scanner.map))
+    }
+
+    /**
+    Parses `text` in given `format`, recording the position of every value.
+
+    Positions are recorded for json, jsonc and yaml. For yaml, `text`
+    must contain single document.
+    */
+    pub fn parse(text: &str, format: SourceFormat) -> Result<(Value, SourceMap), ParseError> {
+        let mut docs = Self::parse_all(text, format)?;
+        match docs.len() {
+            1 => Ok(docs.remove(0)),
+            n => Err(ParseError::new(
+                format!("want single document, but got {n}"),
+                None,
+            )),
+        }
+    }
+
+    // parses all documents in `text`
+    pub(crate) fn parse_all(
+        text: &str,
+        format: SourceFormat,
+    ) -> Result<Vec<(Value, SourceMap)>, ParseError> {
+        #[allow(unreachable_patterns)]
+        match format {
+            SourceFormat::Json => Ok(vec![Self::parse_json(text)?]),
+            SourceFormat::Jsonc => Ok(vec![Self::parse_json(&strip_jsonc(text))?]),
+            #[cfg(feature = "json5")]
+            SourceFormat::Json5 => match json5::from_str(text) {
+                Ok(v) => Ok(vec![(v, SourceMap::default())]),
+                Err(json5::Error::Message { msg, location }) => {
+                    let position = location.map(|loc| Position {
+                        line: loc.line,
+                        column: loc.column,
+                    });
+                    Err(ParseError::new(msg, position))
+                }
+            },
+            #[cfg(feature = "toml")]
+            SourceFormat::Toml => match toml::from_str(text) {
+                Ok(v) => Ok(vec![(from_toml(v), SourceMap::default())]),
+                Err(e) => {
+                    let position = e.span().map(|span| offset_position(text, span.start));
+                    Err(ParseError::new(
+                        e.message().trim_end().replace('\n', "; "),
+                        position,
+                    ))
+                }
+            },
+            #[cfg(feature = "yaml")]
+            SourceFormat::Yaml => yaml::parse(text),
+            _ => Err(ParseError::new(
+                format!("enable feature {format:?} to parse").to_lowercase(),
+                None,
+            )),
+        }
+    }
+
+    // parses all documents in `text`, without recording positions
+    pub(crate) fn parse_values(text: &str, format: SourceFormat) -> Result<Vec<Value>, ParseError> {
+        match format {
+            SourceFormat::Json => Ok(vec![serde_json::from_str(text)?]),
+            SourceFormat::Jsonc => Ok(vec![serde_json::from_str(&strip_jsonc(text))?]),
+            _ => {
+                let docs = Self::parse_all(text, format)?;
+                Ok(docs.into_iter().map(|(v, _)| v).collect())
+            }
+        }
+    }
+
+    /**
+    Parses yaml `text`, recording the position of every value.
+
+    `text` must contain single document. Scalars are resolved using
+    the core schema of YAML 1.2; mapping keys must be scalars.
+    */
+    #[cfg(feature = "yaml")]
+    pub fn parse_yaml(text: &str) -> Result<(Value, SourceMap), Box<dyn Error>> {
+        Ok(Self::parse(text, SourceFormat::Yaml)?)
+    }
+
+    /// Returns position of value at json-pointer `ptr`.
+    pub fn get(&self, ptr: &str) -> Option<Position> {
+        self.positions.get(ptr).copied()
+    }
+
+    /// Returns position of value at `loc`.
+    pub fn position(&self, loc: &InstanceLocation) -> Option<Position> {
+        self.get(&loc.to_string())
+    }
+
+    /// Returns number of values recorded.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Returns true if no values are recorded.
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    // returns positions of value at `ptr`, relative to that value
+    pub(crate) fn subtree(&self, ptr: &str) -> SourceMap {
+        let positions = self
+            .positions
+            .iter()
+            .filter_map(|(p, pos)| {
+                let rest = p.strip_prefix(ptr)?;
+                (rest.is_empty() || rest.starts_with('/')).then(|| (rest.to_owned(), *pos))
+            })
+            .collect();
+        SourceMap { positions }
+    }
+
+    fn insert(&mut self, ptr: &str, pos: Position) {
+        self.positions.insert(ptr.to_owned(), pos);
+    }
+}
+
+/// Source text of a parsed document. Its [`SourceMap`] is computed
+/// on first use, rather than kept for every loaded document.
+#[derive(Debug)]
+pub(crate) struct Source {
+    text: Arc<str>,
+    format: SourceFormat,
+    index: usize, // of document in text
+    map: OnceCell<SourceMap>,
+}
+
+impl Source {
+    pub(crate) fn new(text: Arc<str>, format: SourceFormat, index: usize) -> Self {
+        Self {
+            text,
+            format,
+            index,
+            map: OnceCell::new(),
+        }
+    }
+
+    /// Source whose positions are already known.
+    pub(crate) fn from_map(map: SourceMap) -> Self {
+        Self {
+            text: "".into(),
+            format: SourceFormat::Json,
+            index: 0,
+            map: OnceCell::with_value(map),
+        }
+    }
+
+    pub(crate) fn map(&self) -> &SourceMap {
+        self.map.get_or_init(|| {
+            // text is already parsed once without error
+            let docs = SourceMap::parse_all(&self.text, self.format).unwrap_or_default();
+            docs.into_iter()
+                .nth(self.index)
+                .map(|(_, map)| map)
+                .unwrap_or_default()
+        })
+    }
+}
+
+// replaces comments and trailing commas with spaces, so that
+// positions of values are preserved
+fn strip_jsonc(text: &str) -> String {
+    #[derive(PartialEq)]
+    enum State {
+        Normal,
+        String,
+        Escape,
+        LineComment,
+        BlockComment,
+    }
+    let mut out: Vec<char> = Vec::with_capacity(text.len());
+    let mut comma = None; // index of last comma in out
+    let mut state = State::Normal;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match state {
+            State::Normal => match (c, chars.peek()) {
+                ('/', Some('/')) => state = State::LineComment,
+                ('/', Some('*')) => {
+                    chars.next();
+                    out.push(' ');
+                    state = State::BlockComment;
+                }
+                _ => {
+                    if c == '}' || c == ']' {
+                        if let Some(i) = comma.take() {
+                            out[i] = ' ';
+                        }
+                    } else if !c.is_whitespace() {
+                        comma = None;
+                    }
+                    if c == ',' {
+                        comma = Some(out.len());
+                    } else if c == '"' {
+                        state = State::String;
+                    }
+                }
+            },
+            State::String => match c {
+                '\\' => state = State::Escape,
+                '"' => state = State::Normal,
+                _ => {}
+            },
+            State::Escape => state = State::String,
+            State::LineComment => {
+                if c == '\n' {
+                    state = State::Normal;
+                }
+            }
+            State::BlockComment => {
+                if c == '*' && chars.peek() == Some(&'/') {
+                    chars.next();
+                    out.push(' ');
+                    out.push(' ');
+                    state = State::Normal;
+                    continue;
+                }
+            }
+        }
+        let comment = matches!(state, State::LineComment | State::BlockComment);
+        out.push(if comment && c != '\n' { ' ' } else { c });
+    }
+    out.into_iter().collect()
+}
+
+#[cfg(feature = "toml")]
+fn from_toml(v: toml::Value) -> Value {
+    match v {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::from(i),
+        toml::Value::Float(f) => match serde_json::Number::from_f64(f) {
+            Some(n) => Value::Number(n),
+            None => Value::String(f.to_string()),
+        },
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(dt) => Value::String(dt.to_string()),
+        toml::Value::Array(arr) => Value::Array(arr.into_iter().map(from_toml).collect()),
+        toml::Value::Table(t) => {
+            Value::Object(t.into_iter().map(|(k, v)| (k, from_toml(v))).collect())
+        }
+    }
+}
+
+#[cfg(feature = "toml")]
+fn offset_position(text: &str, offset: usize) -> Position {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Position {
+        line: before.matches('\n').count() + 1,
+        column: before[line_start..].chars().count() + 1,
+    }
+}
+
+// records positions in text already validated by serde_json
+struct JsonScanner<'a> {
+    text: &'a [u8],
+    offset: usize,
+    pos: Position,
+    map: SourceMap,
+}
+
+impl<'a> JsonScanner<'a> {
+    fn peek(&self) -> u8 {
+        self.text.get(self.offset).copied().unwrap_or_default()
+    }
+
+    fn bump(&mut self) -> u8 {
+        let b = self.peek();
+        self.offset += 1;
+        if b == b'\n' {
+            self.pos.line += 1;
+            self.pos.column = 1;
+        } else if b & 0xC0 != 0x80 {
+            // not utf-8 continuation byte
+            self.pos.column += 1;
+        }
+        b
+    }
+
+    fn skip_ws(&mut self) {
+        while matches!(self.peek(), b' ' | b'\t' | b'\n' | b'\r') {
+            self.bump();
+        }
+    }
+
+    fn value(&mut self, ptr: &mut String) {
+        self.skip_ws();
+        self.map.insert(ptr, self.pos);
+        match self.peek() {
+            b'{' => {
+                self.bump();
+                self.skip_ws();
+                if self.peek() == b'}' {
+                    self.bump();
+                    return;
+                }
+                loop {
+                    self.skip_ws();
+                    let key = self.string();
+                    self.skip_ws();
+                    self.bump(); // colon
+                    let len = ptr.len();
+                    ptr.push('/');
+                    ptr.push_str(&escape(&key));
+                    self.value(ptr);
+                    ptr.truncate(len);
+                    self.skip_ws();
+                    if self.bump() != b',' {
+                        return;
+                    }
+                }
+            }
+            b'[' => {
+                self.bump();
+                self.skip_ws();
+                if self.peek() == b']' {
+                    self.bump();
+                    return;
+                }
+                for i in 0.. {
+                    let len = ptr.len();
+                    ptr.push('/');
+                    ptr.push_str(&i.to_string());
+                    self.value(ptr);
+                    ptr.truncate(len);
+                    self.skip_ws();
+                    if self.bump() != b',' {
+                        return;
+                    }
+                }
+            }
+            b'"' => {
+                self.string();
+            }
+            _ => {
+                while !matches!(
+                    self.peek(),
+                    0 | b',' | b'}' | b']' | b' ' | b'\t' | b'\n' | b'\r'
+                ) {
+                    self.bump();
+                }
+            }
+        }
+    }
+
+    // returns decoded string
+    fn string(&mut self) -> String {
+        let start = self.offset;
+        self.bump(); // quote
+        loop {
+            match self.bump() {
+                b'\\' => {
+                    self.bump();
+                }
+                b'"' | 0 => break,
+                _ => {}
+            }
+        }
+        let raw = std::str::from_utf8(&self.text[start..self.offset]).unwrap_or_default();
+        serde_json::from_str(raw).unwrap_or_default()
+    }
+}
+
+#[cfg(feature = "yaml")]
+pub(crate) mod yaml {
+    use std::collections::HashMap;
+
+    use serde_json::{Map, Number, Value};
+    use yaml_rust2::{
+        parser::{Event, MarkedEventReceiver, Parser, Tag},
+        scanner::{Marker, TScalarStyle},
+    };
+
+    use super::{ParseError, Position, SourceMap};
+    use crate::util::*;
+
+    // max nodes produced by expanding aliases, to reject "billion laughs"
+    const MAX_ALIAS_NODES: usize = 100_000;
+
+    // parses all documents in `text`
+    pub(crate) fn parse(text: &str) -> Result<Vec<(Value, SourceMap)>, ParseError> {
+        let mut builder = Builder::default();
+        Parser::new_from_str(text)
+            .load(&mut builder, true)
+            .map_err(|e| ParseError::new(e.info(), Some(position(*e.marker()))))?;
+        match builder.error {
+            Some(e) => Err(e),
+            None => Ok(builder.docs),
+        }
+    }
+
+    struct Frame {
+        value: Value,
+        anchor: usize,
+        key: Option<String>, // pending key of mapping
+        ptr_len: usize,      // length of `Builder::ptr` before this frame
+        nodes: usize,        // `Builder::nodes` before this frame
+    }
+
+    #[derive(Default)]
+    struct Builder {
+        docs: Vec<(Value, SourceMap)>,
+        map: SourceMap,
+        stack: Vec<Frame>,
+        ptr: String,                             // json-pointer of top frame
+        anchors: HashMap<usize, (Value, usize)>, // anchor => (value, number of nodes)
+        nodes: usize,                            // number of nodes added
+        alias_nodes: usize,                      // number of nodes added by aliases
+        error: Option<ParseError>,
+    }
+
+    impl Builder {
+        // records position of node starting at `mark`, and appends its token to `ptr`.
+        // returns length of `ptr` before appending, or `None` if node is a mapping key.
+        fn start(&mut self, mark: Marker) -> Option<usize> {
+            let tok = match self.stack.last() {
+                Some(Frame {
+                    value: Value::Object(_),
+                    key,
+                    ..
+                }) => escape(key.as_ref()?).into_owned(),
+                Some(Frame {
+                    value: Value::Array(arr),
+                    ..
+                }) => arr.len().to_string(),
+                _ => String::new(),
+            };
+            let len = self.ptr.len();
+            if !self.stack.is_empty() {
+                self.ptr.push('/');
+                self.ptr.push_str(&tok);
+            }
+            self.map.insert(&self.ptr, position(mark));
+            Some(len)
+        }
+
+        fn push(&mut self, value: Value, anchor: usize, mark: Marker) -> Result<(), String> {
+            let Some(ptr_len) = self.start(mark) else {
+                return Err("non-scalar key".to_owned());
+            };
+            self.stack.push(Frame {
+                value,
+                anchor,
+                key: None,
+                ptr_len,
+                nodes: self.nodes,
+            });
+            Ok(())
+        }
+
+        fn pop(&mut self) -> Result<(), String> {
+            let Some(frame) = self.stack.pop() else {
+                return Err("unbalanced yaml events".to_owned());
+            };
+            self.ptr.truncate(frame.ptr_len);
+            self.add(frame.value, frame.anchor, self.nodes - frame.nodes + 1);
+            Ok(())
+        }
+
+        // adds node value having `nodes` nodes to its parent
+        fn add(&mut self, value: Value, anchor: usize, nodes: usize) {
+            self.nodes += nodes;
+            if anchor > 0 {
+                self.anchors.insert(anchor, (value.clone(), nodes));
+            }
+            match self.stack.last_mut() {
+                Some(Frame {
+                    value: Value::Array(arr),
+                    ..
+                }) => arr.push(value),
+                Some(Frame {
+                    value: Value::Object(obj),
+                    key,
+                    ..
+                }) => {
+                    if let Some(key) = key.take() {
+                        obj.insert(key, value);
+                    }
+                }
+                _ => {
+                    let map = std::mem::take(&mut self.map);
+                    self.docs.push((value, map));
+                }
+            }
+        }
+
+        fn on_event(&mut self, ev: Event, mark: Marker) -> Result<(), String> {
+            match ev {
+                Event::MappingStart(anchor, _) => {
+                    self.push(Value::Object(Map::new()), anchor, mark)
+                }
+                Event::SequenceStart(anchor, _) => self.push(Value::Array(vec![]), anchor, mark),
+                Event::MappingEnd | Event::SequenceEnd => self.pop(),
+                Event::Scalar(s, style, anchor, tag) => {
+                    let Some(len) = self.start(mark) else {
+                        if let Some(frame) = self.stack.last_mut() {
+                            // mark of block mapping is at colon of its first key
+                            if matches!(&frame.value, Value::Object(obj) if obj.is_empty()) {
+                                let pos = position(mark);
+                                if let Some(p) = self.map.positions.get_mut(&self.ptr) {
+                                    if (pos.line, pos.column) < (p.line, p.column) {
+                                        *p = pos;
+                                    }
+                                }
+                            }
+                            frame.key = Some(s);
+                        }
+                        return Ok(());
+                    };
+                    self.ptr.truncate(len);
+                    self.add(resolve(s, style, tag), anchor, 1);
+                    Ok(())
+                }
+                Event::Alias(anchor) => {
+                    let Some((value, nodes)) = self.anchors.get(&anchor) else {
+                        return Err("unknown anchor".to_owned());
+                    };
+                    let nodes = *nodes;
+                    self.alias_nodes += nodes;
+                    if self.alias_nodes > MAX_ALIAS_NODES {
+                        return Err(format!(
+                            "aliases expand to more than {MAX_ALIAS_NODES} nodes"
+                        ));
+                    }
+                    let value = value.clone();
+                    let Some(len) = self.start(mark) else {
+                        return Err("alias key".to_owned());
+                    };
+                    self.ptr.truncate(len);
+                    self.add(value, 0, nodes);
+                    Ok(())
+                }
+                _ => Ok(()),
+            }
+        }
+    }
+
+    impl MarkedEventReceiver for Builder {
+        fn on_event(&mut self, ev: Event, mark: Marker) {
+            if self.error.is_none() {
+                if let Err(e) = Builder::on_event(self, ev, mark) {
+                    self.error = Some(ParseError::new(e, Some(position(mark))));
+                }
+            }
+        }
+    }
+
+    fn position(mark: Marker) -> Position {
+        Position {
+            line: mark.line(),
+            column: mark.col() + 1,
+        }
+    }
+
+    // resolves scalar using core schema of yaml 1.2
+    fn resolve(s: String, style: TScalarStyle, tag: Option<Tag>) -> Value {
+        if style != TScalarStyle::Plain || tag.is_some_and(|t| t.suffix == "str") {
+            return Value::String(s);
+        }
+        match s.as_str() {
+            "" | "~" | "null" | "Null" | "NULL" => return Value::Null,
+            "true" | "True" | "TRUE" => return Value::Bool(true),
+            "false" | "False" | "FALSE" => return Value::Bool(false),
+            _ => {}
+        }
+        let digits = s.strip_prefix(['-', '+']).unwrap_or(&s);
+        if digits.bytes().all(|b| b.is_ascii_digit()) && !digits.is_empty() {
+            if let Ok(n) = s.parse::<i64>() {
+                return Value::Number(n.into());
+            }
+            if let Ok(n) = digits.parse::<u64>() {
+                if !s.starts_with('-') {
+                    return Value::Number(n.into());
+                }
+            }
+        }
+        if let Some(hex) = s.strip_prefix("0x") {
+            if let Ok(n) = i64::from_str_radix(hex, 16) {
+                return Value::Number(n.into());
+            }
+        }
+        if let Some(oct) = s.strip_prefix("0o") {
+            if let Ok(n) = i64::from_str_radix(oct, 8) {
+                return Value::Number(n.into());
+            }
+        }
+        if s.bytes().any(|b| b.is_ascii_digit()) {
+            if let Some(n) = s.parse::<f64>().ok().and_then(Number::from_f64) {
+                return Value::Number(n);
+            }
+        }
+        Value::String(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_json() {
+        let text = "{\n  \"a\": [1, \"ü\", {\"x/y\": null}],\n\t\"b\" : true\n}";
+        let (_, map) = SourceMap::parse_json(text).unwrap();
+        let tests = [
+            ("", 1, 1),
+            ("/a", 2, 8),
+            ("/a/0", 2, 9),
+            ("/a/1", 2, 12),
+            ("/a/2", 2, 17),
+            ("/a/2/x~1y", 2, 25),
+            ("/b", 3, 8),
+        ];
+        for (ptr, line, column) in tests {
+            assert_eq!(map.get(ptr), Some(Position { line, column }), "{ptr}");
+        }
+        assert_eq!(map.len(), tests.len());
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_parse_yaml() {
+        let text = "a:\n  - 1\n  - &x {b: 'c'}\n  - *x\nd: 1.5\ne: \"1\"\nf: 0x10\ng:\n  hh: 1\n";
+        let (value, map) = SourceMap::parse_yaml(text).unwrap();
+        let want = serde_json::json!({
+            "a": [1, {"b": "c"}, {"b": "c"}],
+            "d": 1.5,
+            "e": "1",
+            "f": 16,
+            "g": {"hh": 1},
+        });
+        assert_eq!(value, want);
+        let tests = [
+            ("", 1, 1),
+            ("/a", 2, 3),
+            ("/a/0", 2, 5),
+            ("/a/1", 3, 8),
+            ("/a/1/b", 3, 12),
+            ("/a/2", 4, 5),
+            ("/d", 5, 4),
+            ("/g", 9, 3),
+        ];
+        for (ptr, line, column) in tests {
+            assert_eq!(map.get(ptr), Some(Position { line, column }), "{ptr}");
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_parse_yaml_aliases() {
+        let mut text = "a0: &a0 [x, x, x, x, x, x, x, x, x, x]\n".to_owned();
+        for i in 1..10 {
+            let j = i - 1;
+            text.push_str(&format!(
+                "a{i}: &a{i} [*a{j}, *a{j}, *a{j}, *a{j}, *a{j}]\n"
+            ));
+        }
+        let Err(e) = SourceMap::parse_yaml(&text) else {
+            panic!("billion laughs must fail");
+        };
+        assert!(e.to_string().contains("aliases expand"), "{e}");
+
+        // moderate use of aliases is fine
+        let text = "a: &a {type: string}\nb: *a\nc: [*a, *a]\n";
+        assert!(SourceMap::parse_yaml(text).is_ok());
+    }
+}
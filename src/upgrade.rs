@@ -0,0 +1,595 @@
+use std::{collections::HashSet, error::Error, fmt::Display, str::FromStr};
+
+use serde_json::{Map, Value};
+
+use crate::{draft::*, util::*};
+
+// anchor name used when translating `$recursiveAnchor` into `$dynamicAnchor`
+const RECURSIVE_ANCHOR: &str = "meta";
+
+/// Error returned by [`Draft::upgrade`](crate::Draft::upgrade).
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum UpgradeError {
+    /// `$recursiveAnchor` cannot be translated into `$dynamicAnchor`,
+    /// because the document already uses `anchor`.
+    AnchorConflict { anchor: String },
+}
+
+impl Error for UpgradeError {}
+
+impl Display for UpgradeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AnchorConflict { anchor } => {
+                write!(
+                    f,
+                    "cannot translate $recursiveAnchor, anchor {anchor:?} is already used"
+                )
+            }
+        }
+    }
+}
+
+impl Draft {
+    // rewrites schema document `doc` written in this draft into draft 2020-12.
+    // if `doc` has `$schema` of a known draft, that draft is used instead.
+    pub(crate) fn upgrade(&'static self, doc: &Value) -> Result<Value, UpgradeError> {
+        let mut draft = self;
+        if let Value::Object(obj) = doc {
+            if let Some(Value::String(sch)) = obj.get("$schema") {
+                if let Some(d) = Draft::from_url(sch) {
+                    draft = d;
+                }
+            }
+        }
+        if draft.version > DRAFT2020.version {
+            return Ok(doc.clone()); // never downgrade
+        }
+        if draft.version < DRAFT2020.version && anchor_conflict(doc) {
+            return Err(UpgradeError::AnchorConflict {
+                anchor: RECURSIVE_ANCHOR.to_owned(),
+            });
+        }
+        let mut v = Upgrader { draft }.schema(doc, false);
+        if let Value::Object(obj) = &mut v {
+            obj.insert("$schema".into(), DRAFT2020.url.into());
+        }
+        Ok(v)
+    }
+}
+
+struct Upgrader {
+    draft: &'static Draft,
+}
+
+// tells whether `doc` uses `$recursiveAnchor`, and RECURSIVE_ANCHOR as anchor
+fn anchor_conflict(doc: &Value) -> bool {
+    fn collect<'a>(v: &'a Value, anchors: &mut HashSet<&'a str>, recursive: &mut bool) {
+        match v {
+            Value::Object(obj) => {
+                for (pname, pvalue) in obj {
+                    match (pname.as_str(), pvalue) {
+                        ("$anchor" | "$dynamicAnchor", Value::String(s)) => _ = anchors.insert(s),
+                        ("id" | "$id", Value::String(s)) => _ = anchors.insert(split(s).1),
+                        ("$recursiveAnchor", Value::Bool(true)) => *recursive = true,
+                        _ => {}
+                    }
+                    collect(pvalue, anchors, recursive);
+                }
+            }
+            Value::Array(arr) => arr
+                .iter()
+                .for_each(|item| collect(item, anchors, recursive)),
+            _ => {}
+        }
+    }
+    let (mut anchors, mut recursive) = (HashSet::new(), false);
+    collect(doc, &mut anchors, &mut recursive);
+    recursive && anchors.contains(RECURSIVE_ANCHOR)
+}
+
+impl Upgrader {
+    fn version(&self) -> usize {
+        self.draft.version
+    }
+
+    // `recursive_anchor` tells whether the enclosing resource has `$recursiveAnchor: true`
+    fn schema(&self, sch: &Value, mut recursive_anchor: bool) -> Value {
+        let Value::Object(obj) = sch else {
+            return sch.clone();
+        };
+        if self.version() >= 2020 {
+            return sch.clone();
+        }
+
+        let mut out = Map::new();
+        if self.version() < 2019 {
+            if let Some(ref_) = obj.get("$ref") {
+                // all other properties in a "$ref" object are ignored,
+                // except definitions which can still be referenced
+                out.insert("$ref".into(), self.reference(ref_));
+                if let Some(defs) = obj.get("definitions") {
+                    out.insert("$defs".into(), self.props(defs, recursive_anchor));
+                }
+                return Value::Object(out);
+            }
+        }
+
+        if obj.contains_key(self.draft.id) {
+            // new resource
+            recursive_anchor = false;
+        }
+        if let Some(Value::Bool(b)) = obj.get("$recursiveAnchor") {
+            recursive_anchor = *b;
+        }
+
+        let mut recursive_ref = None; // translated into `$ref`
+        for (pname, pvalue) in obj {
+            match pname.as_str() {
+                "$schema" => {
+                    let known = matches!(pvalue, Value::String(s) if Draft::from_url(s).is_some());
+                    if !known {
+                        out.insert(pname.clone(), pvalue.clone());
+                    }
+                }
+                "id" | "$id" if pname == self.draft.id => {
+                    let Value::String(id) = pvalue else {
+                        out.insert(pname.clone(), pvalue.clone());
+                        continue;
+                    };
+                    let (url, frag) = split(id);
+                    if !url.is_empty() {
+                        out.insert("$id".into(), url.into());
+                    }
+                    if self.version() < 2019 && !frag.is_empty() && !frag.starts_with('/') {
+                        out.insert("$anchor".into(), frag.into());
+                    }
+                }
+                "$ref" => _ = out.insert(pname.clone(), self.reference(pvalue)),
+                "$recursiveRef" => {
+                    if recursive_anchor && pvalue == "#" {
+                        let dref = format!("#{RECURSIVE_ANCHOR}");
+                        out.insert("$dynamicRef".into(), dref.into());
+                    } else {
+                        recursive_ref = Some(self.reference(pvalue));
+                    }
+                }
+                "$recursiveAnchor" => {
+                    if let Value::Bool(true) = pvalue {
+                        out.insert("$dynamicAnchor".into(), RECURSIVE_ANCHOR.into());
+                    }
+                }
+                "definitions" => {
+                    let defs = self.props(pvalue, recursive_anchor);
+                    match out.get_mut("$defs") {
+                        Some(Value::Object(obj)) => {
+                            if let Value::Object(defs) = defs {
+                                for (k, v) in defs {
+                                    obj.entry(k).or_insert(v);
+                                }
+                            }
+                        }
+                        _ => _ = out.insert("$defs".into(), defs),
+                    }
+                }
+                "$defs" => {
+                    let defs = self.props(pvalue, recursive_anchor);
+                    match (out.get_mut("$defs"), defs) {
+                        (Some(Value::Object(obj)), Value::Object(defs)) => obj.extend(defs),
+                        (_, defs) => _ = out.insert("$defs".into(), defs),
+                    }
+                }
+                "items" => match pvalue {
+                    Value::Array(_) => {
+                        out.insert("prefixItems".into(), self.items(pvalue, recursive_anchor));
+                        if let Some(additional) = obj.get("additionalItems") {
+                            let additional = self.schema(additional, recursive_anchor);
+                            out.insert("items".into(), additional);
+                        }
+                    }
+                    _ => _ = out.insert(pname.clone(), self.schema(pvalue, recursive_anchor)),
+                },
+                "additionalItems" => {} // handled with items
+                "dependencies" => {
+                    let Value::Object(deps) = pvalue else {
+                        continue;
+                    };
+                    let (mut dep_req, mut dep_sch) = (Map::new(), Map::new());
+                    for (k, v) in deps {
+                        match v {
+                            Value::Array(_) => _ = dep_req.insert(k.clone(), v.clone()),
+                            Value::String(_) => {
+                                _ = dep_req.insert(k.clone(), vec![v.clone()].into())
+                            }
+                            _ => _ = dep_sch.insert(k.clone(), self.schema(v, recursive_anchor)),
+                        }
+                    }
+                    if !dep_req.is_empty() {
+                        out.insert("dependentRequired".into(), dep_req.into());
+                    }
+                    if !dep_sch.is_empty() {
+                        out.insert("dependentSchemas".into(), dep_sch.into());
+                    }
+                }
+                "minimum" | "maximum" if self.version() <= 4 => {
+                    let ex = match pname.as_str() {
+                        "minimum" => "exclusiveMinimum",
+                        _ => "exclusiveMaximum",
+                    };
+                    if let Some(Value::Bool(true)) = obj.get(ex) {
+                        out.insert(ex.into(), pvalue.clone());
+                    } else {
+                        out.insert(pname.clone(), pvalue.clone());
+                    }
+                }
+                "exclusiveMinimum" | "exclusiveMaximum" if self.version() <= 4 => {
+                    if !matches!(pvalue, Value::Bool(_)) {
+                        out.insert(pname.clone(), pvalue.clone());
+                    }
+                }
+                "required" if self.version() == 3 => {} // moved into parent
+                "divisibleBy" if self.version() == 3 => {
+                    out.insert("multipleOf".into(), pvalue.clone());
+                }
+                "extends" if self.version() == 3 => {
+                    let all_of = match pvalue {
+                        Value::Array(_) => self.items(pvalue, recursive_anchor),
+                        _ => vec![self.schema(pvalue, recursive_anchor)].into(),
+                    };
+                    out.insert("allOf".into(), all_of);
+                }
+                "type" if self.version() == 3 => {
+                    if let Some(types) = self.types(pvalue, recursive_anchor) {
+                        out.extend(types);
+                    }
+                }
+                "disallow" if self.version() == 3 => {
+                    let not = self.types(pvalue, recursive_anchor).unwrap_or_default();
+                    out.insert("not".into(), not.into());
+                }
+                "nullable" if self.draft.dialect == Dialect::OpenApi30 => {} // merged into type
+                _ => {
+                    let pos = self.draft.subschemas.get(pname.as_str()).cloned();
+                    let v = match pos {
+                        Some(pos) if pos & POS_PROP != 0 => self.props(pvalue, recursive_anchor),
+                        Some(pos) if pos & POS_ITEM != 0 && pvalue.is_array() => {
+                            self.items(pvalue, recursive_anchor)
+                        }
+                        Some(pos) if pos & POS_SELF != 0 => self.schema(pvalue, recursive_anchor),
+                        _ => pvalue.clone(),
+                    };
+                    out.insert(pname.clone(), v);
+                }
+            }
+        }
+
+        if let Some(ref_) = recursive_ref {
+            if out.contains_key("$ref") {
+                // both refs apply
+                let mut sch = Map::new();
+                sch.insert("$ref".into(), ref_);
+                match out.get_mut("allOf") {
+                    Some(Value::Array(all_of)) => all_of.push(sch.into()),
+                    _ => _ = out.insert("allOf".into(), vec![Value::from(sch)].into()),
+                }
+            } else {
+                out.insert("$ref".into(), ref_);
+            }
+        }
+
+        if self.version() == 3 {
+            // required is specified as boolean in property schema
+            if let Some(Value::Object(props)) = obj.get("properties") {
+                let required = props
+                    .iter()
+                    .filter(|(_, v)| matches!(v.get("required"), Some(Value::Bool(true))))
+                    .map(|(k, _)| Value::String(k.clone()))
+                    .collect::<Vec<_>>();
+                if !required.is_empty() {
+                    out.insert("required".into(), required.into());
+                }
+            }
+        }
+        if self.draft.dialect == Dialect::OpenApi30 {
+            if let (Some(Value::Bool(true)), Some(t)) = (obj.get("nullable"), out.get_mut("type")) {
+                if let Value::String(_) = t {
+                    *t = vec![t.clone(), "null".into()].into();
+                }
+            }
+        }
+        Value::Object(out)
+    }
+
+    // converts draft3 `type`/`disallow` value into schema with `type`,
+    // or with `anyOf` when it contains schemas.
+    // returns None, if `any` type is used.
+    fn types(&self, v: &Value, recursive_anchor: bool) -> Option<Map<String, Value>> {
+        let arr = match v {
+            Value::Array(arr) => arr.as_slice(),
+            _ => std::slice::from_ref(v),
+        };
+        let (mut types, mut schemas) = (vec![], vec![]);
+        for item in arr {
+            match item {
+                Value::String(t) if t == "any" => return None,
+                Value::String(_) => types.push(item.clone()),
+                _ => schemas.push(self.schema(item, recursive_anchor)),
+            }
+        }
+        let types = match types.len() {
+            1 => types.remove(0),
+            _ => types.into(),
+        };
+        let mut obj = Map::new();
+        if types != Value::Array(vec![]) {
+            obj.insert("type".into(), types);
+        }
+        if schemas.is_empty() {
+            return Some(obj);
+        }
+        if !obj.is_empty() {
+            schemas.insert(0, obj.into());
+        }
+        let mut obj = Map::new();
+        obj.insert("anyOf".into(), schemas.into());
+        Some(obj)
+    }
+
+    fn props(&self, v: &Value, recursive_anchor: bool) -> Value {
+        let Value::Object(obj) = v else {
+            return v.clone();
+        };
+        obj.iter()
+            .map(|(k, v)| (k.clone(), self.schema(v, recursive_anchor)))
+            .collect::<Map<_, _>>()
+            .into()
+    }
+
+    fn items(&self, v: &Value, recursive_anchor: bool) -> Value {
+        let Value::Array(arr) = v else {
+            return v.clone();
+        };
+        arr.iter()
+            .map(|item| self.schema(item, recursive_anchor))
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    fn reference(&self, ref_: &Value) -> Value {
+        let Value::String(ref_) = ref_ else {
+            return ref_.clone();
+        };
+        let (url, frag) = split(ref_);
+        if !url.is_empty() || !frag.starts_with('/') {
+            // pointers into other documents are left as they are
+            return ref_.as_str().into();
+        }
+        format!("{url}#{}", self.pointer(frag)).into()
+    }
+
+    // translates json-pointer into this draft's document to the
+    // json-pointer into the upgraded document.
+    fn pointer(&self, ptr: &str) -> String {
+        let mut out = String::new();
+        let mut tokens = ptr.split('/').skip(1).peekable();
+        let mut push = |tok: &str| {
+            out.push('/');
+            out.push_str(tok);
+        };
+        let is_index = |tok: Option<&&str>| tok.filter(|t| usize::from_str(t).is_ok()).is_some();
+        while let Some(tok) = tokens.next() {
+            match tok {
+                "extends" if self.version() == 3 => {
+                    push("allOf");
+                    if !is_index(tokens.peek()) {
+                        push("0");
+                    }
+                }
+                "definitions" | "dependencies" => {
+                    push(if tok == "definitions" {
+                        "$defs"
+                    } else {
+                        "dependentSchemas"
+                    });
+                    if let Some(prop) = tokens.next() {
+                        push(prop);
+                    }
+                }
+                "items" if self.version() < 2020 && is_index(tokens.peek()) => {
+                    push("prefixItems");
+                    if let Some(index) = tokens.next() {
+                        push(index);
+                    }
+                }
+                "additionalItems" => push("items"),
+                _ => match self.draft.subschemas.get(tok) {
+                    Some(&pos) => {
+                        push(tok);
+                        if pos & POS_PROP != 0 || (pos & POS_ITEM != 0 && is_index(tokens.peek())) {
+                            if let Some(tok) = tokens.next() {
+                                push(tok);
+                            }
+                        }
+                    }
+                    None => {
+                        // not a subschema location; copy rest as it is
+                        push(tok);
+                        for tok in tokens.by_ref() {
+                            push(tok);
+                        }
+                    }
+                },
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{Compiler, Schemas, UpgradeError};
+
+    #[test]
+    fn test_upgrade_draft4() {
+        let doc = json!({
+            "$schema": "http://json-schema.org/draft-04/schema#",
+            "id": "http://a.com/schema.json",
+            "definitions": {
+                "pos": { "id": "#pos", "type": "number", "minimum": 0, "exclusiveMinimum": true },
+                "tuple": {
+                    "items": [{ "$ref": "#/definitions/pos" }, { "type": "string" }],
+                    "additionalItems": false
+                }
+            },
+            "properties": {
+                "a": { "$ref": "#/definitions/tuple/items/0", "type": "string" },
+                "b": { "maximum": 5, "exclusiveMaximum": false }
+            },
+            "dependencies": {
+                "a": ["b"],
+                "b": { "required": ["a"] }
+            }
+        });
+        let want = json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "$id": "http://a.com/schema.json",
+            "$defs": {
+                "pos": { "$anchor": "pos", "type": "number", "exclusiveMinimum": 0 },
+                "tuple": {
+                    "prefixItems": [{ "$ref": "#/$defs/pos" }, { "type": "string" }],
+                    "items": false
+                }
+            },
+            "properties": {
+                "a": { "$ref": "#/$defs/tuple/prefixItems/0" },
+                "b": { "maximum": 5 }
+            },
+            "dependentRequired": { "a": ["b"] },
+            "dependentSchemas": { "b": { "required": ["a"] } }
+        });
+        assert_eq!(crate::Draft::V7.upgrade(&doc).unwrap(), want);
+    }
+
+    #[test]
+    fn test_upgrade_draft3() {
+        let doc = json!({
+            "$schema": "http://json-schema.org/draft-03/schema#",
+            "type": ["object", { "type": "string" }],
+            "properties": {
+                "a": { "type": "any", "required": true, "divisibleBy": 2 },
+                "b": { "disallow": "null", "extends": { "$ref": "#/properties/a" } }
+            },
+            "dependencies": { "a": "b" }
+        });
+        let want = json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "anyOf": [{ "type": "object" }, { "type": "string" }],
+            "properties": {
+                "a": { "multipleOf": 2 },
+                "b": { "not": { "type": "null" }, "allOf": [{ "$ref": "#/properties/a" }] }
+            },
+            "required": ["a"],
+            "dependentRequired": { "a": ["b"] }
+        });
+        assert_eq!(crate::Draft::V4.upgrade(&doc).unwrap(), want);
+    }
+
+    #[test]
+    fn test_upgrade_draft2019() {
+        let doc = json!({
+            "$schema": "https://json-schema.org/draft/2019-09/schema",
+            "$recursiveAnchor": true,
+            "properties": {
+                "tree": { "$recursiveRef": "#" },
+                "list": { "items": [{ "$ref": "#" }] }
+            }
+        });
+        let want = json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "$dynamicAnchor": "meta",
+            "properties": {
+                "tree": { "$dynamicRef": "#meta" },
+                "list": { "prefixItems": [{ "$ref": "#" }] }
+            }
+        });
+        assert_eq!(crate::Draft::V4.upgrade(&doc).unwrap(), want);
+    }
+
+    #[test]
+    fn test_upgrade_draft2019_anchor_conflict() {
+        let doc = json!({
+            "$schema": "https://json-schema.org/draft/2019-09/schema",
+            "$recursiveAnchor": true,
+            "properties": {
+                "tree": { "$recursiveRef": "#" },
+                "a": { "$anchor": "meta" }
+            }
+        });
+        let result = crate::Draft::V4.upgrade(&doc);
+        assert!(matches!(result, Err(UpgradeError::AnchorConflict { .. })));
+    }
+
+    #[test]
+    fn test_upgrade_draft2019_refs() {
+        let doc = json!({
+            "$schema": "https://json-schema.org/draft/2019-09/schema",
+            "definitions": { "a": {} },
+            "properties": {
+                "a": { "$ref": "#/definitions/a", "$recursiveRef": "#/definitions/a" },
+                "b": { "$ref": "other.json#/definitions/a" }
+            }
+        });
+        let want = json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "$defs": { "a": {} },
+            "properties": {
+                "a": { "$ref": "#/$defs/a", "allOf": [{ "$ref": "#/$defs/a" }] },
+                "b": { "$ref": "other.json#/definitions/a" }
+            }
+        });
+        assert_eq!(crate::Draft::V4.upgrade(&doc).unwrap(), want);
+    }
+
+    #[test]
+    fn test_upgrade_openapi30() {
+        let doc = json!({
+            "properties": {
+                "a": { "type": "integer", "nullable": true, "minimum": 0, "exclusiveMinimum": true },
+                "b": { "nullable": true }
+            }
+        });
+        let want = json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "properties": {
+                "a": { "type": ["integer", "null"], "exclusiveMinimum": 0 },
+                "b": {}
+            }
+        });
+        assert_eq!(crate::Draft::OpenApi3_0.upgrade(&doc).unwrap(), want);
+    }
+
+    #[test]
+    fn test_upgrade_compiles() {
+        let doc = json!({
+            "definitions": {
+                "pos": { "type": "integer", "minimum": 0, "exclusiveMinimum": true }
+            },
+            "type": "array",
+            "items": [{ "$ref": "#/definitions/pos" }],
+            "additionalItems": { "type": "string" }
+        });
+        let url = "http://a.com/schema.json";
+        let mut schemas = Schemas::new();
+        let mut compiler = Compiler::new();
+        compiler
+            .add_resource(url, crate::Draft::V4.upgrade(&doc).unwrap())
+            .unwrap();
+        let sch = compiler.compile(url, &mut schemas).unwrap();
+        assert!(schemas.validate(&json!([1, "x"]), sch).is_ok());
+        assert!(schemas.validate(&json!([0, "x"]), sch).is_err());
+        assert!(schemas.validate(&json!([1, 2]), sch).is_err());
+    }
+}
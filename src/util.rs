@@ -398,6 +398,22 @@ pub(crate) fn equals(v1: &Value, v2: &Value) -> bool {
     }
 }
 
+// returns indexes of two items, which have same values for `keys`.
+pub(crate) fn duplicate_keys(arr: &[Value], keys: &[String]) -> Option<(usize, usize)> {
+    for i in 1..arr.len() {
+        for j in 0..i {
+            let same = keys.iter().all(|k| match (arr[j].get(k), arr[i].get(k)) {
+                (Some(v1), Some(v2)) => equals(v1, v2),
+                (v1, v2) => v1.is_none() && v2.is_none(),
+            });
+            if same {
+                return Some((j, i));
+            }
+        }
+    }
+    None
+}
+
 pub(crate) fn duplicates(arr: &Vec<Value>) -> Option<(usize, usize)> {
     match arr.as_slice() {
         [e0, e1] => {
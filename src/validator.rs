@@ -1,8 +1,10 @@
-use std::{borrow::Cow, cmp::min, collections::HashSet, fmt::Write};
+use std::{
+    borrow::Cow, cmp::min, collections::HashSet, fmt::Write, sync::atomic::Ordering, time::Instant,
+};
 
 use serde_json::{Map, Value};
 
-use crate::{util::*, *};
+use crate::{messages::expand, util::*, *};
 
 macro_rules! prop {
     ($prop:expr) => {
@@ -20,6 +22,7 @@ pub(crate) fn validate<'s, 'v>(
     v: &'v Value,
     schema: &'s Schema,
     schemas: &'s Schemas,
+    opts: &ValidationOptions,
 ) -> Result<(), ValidationError<'s, 'v>> {
     let scope = Scope {
         sch: schema.idx,
@@ -28,9 +31,11 @@ pub(crate) fn validate<'s, 'v>(
         parent: None,
     };
     let mut vloc = Vec::with_capacity(8);
+    let mut budget = Budget::new(opts);
     let result = Validator {
         v,
         vloc: &mut vloc,
+        budget: &mut budget,
         schema,
         schemas,
         scope,
@@ -39,15 +44,26 @@ pub(crate) fn validate<'s, 'v>(
         bool_result: false,
     }
     .validate();
+    if let Some(abort) = budget.aborted {
+        // errors from subschemas may be ignored by keywords like `not`
+        return Err(ValidationError {
+            schema_url: &schema.loc,
+            instance_location: InstanceLocation::new(),
+            kind: abort.kind(),
+            message: None,
+            causes: vec![],
+        });
+    }
     match result {
         Err(err) => {
             let mut e = ValidationError {
                 schema_url: &schema.loc,
                 instance_location: InstanceLocation::new(),
                 kind: ErrorKind::Schema { url: &schema.loc },
+                message: None,
                 causes: vec![],
             };
-            if let ErrorKind::Group = err.kind {
+            if let (ErrorKind::Group, None) = (&err.kind, &err.message) {
                 e.causes = err.causes;
             } else {
                 e.causes.push(err);
@@ -80,9 +96,71 @@ macro_rules! kind {
     };
 }
 
+// resources used by validation, checked against ValidationOptions
+struct Budget {
+    opts: ValidationOptions,
+    steps: usize,
+    aborted: Option<Abort>,
+}
+
+// reason to abort validation
+#[derive(Clone, Copy)]
+enum Abort {
+    LimitExceeded(Limit),
+    Cancelled,
+}
+
+impl Abort {
+    fn kind<'s, 'v>(self) -> ErrorKind<'s, 'v> {
+        match self {
+            Self::LimitExceeded(limit) => ErrorKind::LimitExceeded { limit },
+            Self::Cancelled => ErrorKind::Cancelled,
+        }
+    }
+}
+
+impl Budget {
+    fn new(opts: &ValidationOptions) -> Self {
+        Self {
+            opts: opts.clone(),
+            steps: 0,
+            aborted: None,
+        }
+    }
+
+    fn abort(&mut self, abort: Abort) {
+        self.aborted.get_or_insert(abort);
+    }
+
+    // counts a schema evaluation at instance `depth`, and returns
+    // the reason to abort, if any.
+    fn check(&mut self, depth: usize) -> Option<Abort> {
+        if self.aborted.is_some() {
+            return self.aborted;
+        }
+        self.steps += 1;
+        let opts = &self.opts;
+        if opts.max_steps.is_some_and(|max| self.steps > max) {
+            self.aborted = Some(Abort::LimitExceeded(Limit::MaxSteps));
+        } else if opts.max_depth.is_some_and(|max| depth > max) {
+            self.aborted = Some(Abort::LimitExceeded(Limit::MaxDepth));
+        } else if opts.deadline.is_some_and(|d| Instant::now() >= d) {
+            self.aborted = Some(Abort::LimitExceeded(Limit::Deadline));
+        } else if opts
+            .cancel
+            .as_ref()
+            .is_some_and(|c| c.load(Ordering::Relaxed))
+        {
+            self.aborted = Some(Abort::Cancelled);
+        }
+        self.aborted
+    }
+}
+
 struct Validator<'v, 's, 'd, 'e> {
     v: &'v Value,
     vloc: &'e mut Vec<InstanceToken<'v>>,
+    budget: &'e mut Budget,
     schema: &'s Schema,
     schemas: &'s Schemas,
     scope: Scope<'d>,
@@ -92,10 +170,38 @@ struct Validator<'v, 's, 'd, 'e> {
 }
 
 impl<'v, 's, 'd, 'e> Validator<'v, 's, 'd, 'e> {
-    fn validate(mut self) -> Result<Uneval<'v>, ValidationError<'s, 'v>> {
+    fn validate(self) -> Result<Uneval<'v>, ValidationError<'s, 'v>> {
+        let all = self
+            .schema
+            .error_message
+            .as_ref()
+            .and_then(|m| m.all.as_ref());
+        match (self.validate_keywords(), all) {
+            (Err(mut e), Some(msg)) => {
+                e.message = Some(custom_message(msg, &e.kind));
+                e.causes.clear();
+                Err(e)
+            }
+            (result, _) => result,
+        }
+    }
+
+    fn validate_keywords(mut self) -> Result<Uneval<'v>, ValidationError<'s, 'v>> {
         let s = self.schema;
         let v = self.v;
 
+        // limits --
+        if let Some(abort) = self.budget.check(self.scope.vid) {
+            // replaced by caller, so instance location is not needed
+            return Err(ValidationError {
+                schema_url: &s.loc,
+                instance_location: InstanceLocation::new(),
+                kind: abort.kind(),
+                message: None,
+                causes: vec![],
+            });
+        }
+
         // boolean --
         if let Some(b) = s.boolean {
             return match b {
@@ -115,11 +221,9 @@ impl<'v, 's, 'd, 'e> Validator<'v, 's, 'd, 'e> {
         }
 
         // type --
-        if !s.types.is_empty() {
+        if !s.types.is_empty() || !s.type_schemas.is_empty() {
             let v_type = Type::of(v);
-            let matched =
-                s.types.contains(v_type) || (s.types.contains(Type::Integer) && is_integer(v));
-            if !matched {
+            if !self.is_type(s.types, &s.type_schemas) {
                 return Err(self.error(kind!(Type, v_type, s.types)));
             }
         }
@@ -140,7 +244,11 @@ impl<'v, 's, 'd, 'e> Validator<'v, 's, 'd, 'e> {
 
         // format --
         if let Some(format) = &s.format {
-            if let Err(e) = (format.func)(v) {
+            let result = match (&s.regex_engine, v) {
+                (Some(engine), Value::String(pattern)) => engine.validate(pattern),
+                _ => (format.func)(v),
+            };
+            if let Err(e) = result {
                 self.add_error(kind!(Format, Cow::Borrowed(v), format.name, e));
             }
         }
@@ -192,6 +300,9 @@ impl<'v, 's, 'd, 'e> Validator<'v, 's, 'd, 'e> {
         macro_rules! add_err {
             ($result:expr) => {
                 if let Err(e) = $result {
+                    if self.aborted() {
+                        return;
+                    }
                     self.errors.push(e);
                 }
             };
@@ -214,7 +325,7 @@ impl<'v, 's, 'd, 'e> Validator<'v, 's, 'd, 'e> {
         // required --
         if !s.required.is_empty() {
             if let Some(missing) = self.find_missing(obj, &s.required) {
-                self.add_error(kind!(Required, want: missing));
+                self.add_required_errors(missing);
             }
         }
 
@@ -248,17 +359,27 @@ impl<'v, 's, 'd, 'e> Validator<'v, 's, 'd, 'e> {
             // properties --
             if let Some(sch) = s.properties.get(pname) {
                 evaluated = true;
-                add_err!(self.validate_val(*sch, pvalue, prop!(pname)));
+                let result = self.validate_val(*sch, pvalue, prop!(pname));
+                add_err!(result.map_err(|e| self.property_error(pname, e)));
             }
 
             // patternProperties --
             for (regex, sch) in &s.pattern_properties {
-                if regex.is_match(pname) {
-                    evaluated = true;
-                    add_err!(self.validate_val(*sch, pvalue, prop!(pname)));
+                match self.is_match(regex, pname) {
+                    Some(true) => {
+                        evaluated = true;
+                        add_err!(self.validate_val(*sch, pvalue, prop!(pname)));
+                    }
+                    Some(false) => {}
+                    None => return,
                 }
             }
 
+            // apiVersion, kind and metadata of kubernetes objects --
+            if !evaluated && s.embedded_resource {
+                evaluated = matches!(pname.as_str(), "apiVersion" | "kind" | "metadata");
+            }
+
             if !evaluated {
                 // additionalProperties --
                 if let Some(additional) = &s.additional_properties {
@@ -284,7 +405,7 @@ impl<'v, 's, 'd, 'e> Validator<'v, 's, 'd, 'e> {
             self.add_error(kind!(AdditionalProperties, got: additional_props));
         }
 
-        if s.draft_version == 4 {
+        if s.draft_version <= 4 {
             return;
         }
 
@@ -321,6 +442,38 @@ impl<'v, 's, 'd, 'e> Validator<'v, 's, 'd, 'e> {
                 }
             }
         }
+
+        if s.draft_version < draft::VERSION_NEXT {
+            return;
+        }
+
+        // propertyDependencies --
+        for (pname, schs) in &s.property_dependencies {
+            if let Some(Value::String(pvalue)) = obj.get(pname) {
+                if let Some(sch) = schs.get(pvalue) {
+                    add_err!(self.validate_self(*sch));
+                }
+            }
+        }
+
+        // contains --
+        if let Some(sch) = &s.contains {
+            let mut matched = vec![];
+            let mut errors = vec![];
+
+            for (i, (pname, pvalue)) in obj.iter().enumerate() {
+                if let Err(e) = self.validate_val(*sch, pvalue, prop!(pname)) {
+                    if self.aborted() {
+                        return;
+                    }
+                    errors.push(e);
+                } else {
+                    matched.push(i);
+                    self.uneval.props.remove(pname);
+                }
+            }
+            self.contains_validate(matched, errors);
+        }
     }
 
     fn arr_validate(&mut self, arr: &'v Vec<Value>) {
@@ -329,6 +482,9 @@ impl<'v, 's, 'd, 'e> Validator<'v, 's, 'd, 'e> {
         macro_rules! add_err {
             ($result:expr) => {
                 if let Err(e) = $result {
+                    if self.aborted() {
+                        return;
+                    }
                     self.errors.push(e);
                 }
             };
@@ -355,6 +511,14 @@ impl<'v, 's, 'd, 'e> Validator<'v, 's, 'd, 'e> {
             }
         }
 
+        // x-kubernetes-list-map-keys --
+        if len > 1 && !s.list_map_keys.is_empty() {
+            if let Some((i, j)) = duplicate_keys(arr, &s.list_map_keys) {
+                let keys = s.list_map_keys.as_slice();
+                self.add_error(ErrorKind::ListMapKeys { keys, got: [i, j] });
+            }
+        }
+
         if s.draft_version < 2020 {
             let mut evaluated = 0;
 
@@ -416,6 +580,9 @@ impl<'v, 's, 'd, 'e> Validator<'v, 's, 'd, 'e> {
 
             for (i, item) in arr.iter().enumerate() {
                 if let Err(e) = self.validate_val(*sch, item, item!(i)) {
+                    if self.aborted() {
+                        return;
+                    }
                     errors.push(e);
                 } else {
                     matched.push(i);
@@ -424,25 +591,32 @@ impl<'v, 's, 'd, 'e> Validator<'v, 's, 'd, 'e> {
                     }
                 }
             }
+            self.contains_validate(matched, errors);
+        }
+    }
 
-            // minContains --
-            if let Some(min) = s.min_contains {
-                if matched.len() < min {
-                    let mut e = self.error(kind!(MinContains, matched.clone(), min));
-                    e.causes = errors;
-                    self.errors.push(e);
-                }
-            } else if matched.is_empty() {
-                let mut e = self.error(kind!(Contains));
+    // `matched` are indexes of values that are valid against contains schema,
+    // and `errors` are the validation errors of remaining values.
+    fn contains_validate(&mut self, matched: Vec<usize>, errors: Vec<ValidationError<'s, 'v>>) {
+        let s = self.schema;
+
+        // minContains --
+        if let Some(min) = s.min_contains {
+            if matched.len() < min {
+                let mut e = self.error(kind!(MinContains, matched.clone(), min));
                 e.causes = errors;
                 self.errors.push(e);
             }
+        } else if matched.is_empty() {
+            let mut e = self.error(kind!(Contains));
+            e.causes = errors;
+            self.errors.push(e);
+        }
 
-            // maxContains --
-            if let Some(max) = s.max_contains {
-                if matched.len() > max {
-                    self.add_error(kind!(MaxContains, matched, max));
-                }
+        // maxContains --
+        if let Some(max) = s.max_contains {
+            if matched.len() > max {
+                self.add_error(kind!(MaxContains, matched, max));
             }
         }
     }
@@ -469,8 +643,10 @@ impl<'v, 's, 'd, 'e> Validator<'v, 's, 'd, 'e> {
 
         // pattern --
         if let Some(regex) = &s.pattern {
-            if !regex.is_match(str) {
-                self.add_error(kind!(Pattern, str.into(), regex.as_str()));
+            match self.is_match(regex, str) {
+                Some(true) => {}
+                Some(false) => self.add_error(kind!(Pattern, str.into(), regex.as_str())),
+                None => return,
             }
         }
 
@@ -571,6 +747,9 @@ impl<'v, 's, 'd, 'e> Validator<'v, 's, 'd, 'e> {
         macro_rules! add_err {
             ($result:expr) => {
                 if let Err(e) = $result {
+                    if self.aborted() {
+                        return;
+                    }
                     self.errors.push(e);
                 }
             };
@@ -589,7 +768,10 @@ impl<'v, 's, 'd, 'e> Validator<'v, 's, 'd, 'e> {
             let mut sch = dref.sch; // initial target
             if let Some(anchor) = &dref.anchor {
                 // $dynamicRef includes anchor
-                if self.schemas.get(sch).dynamic_anchor == dref.anchor {
+                if s.draft_version >= draft::VERSION_NEXT {
+                    // no bookending required
+                    sch = self.resolve_dynamic_anchor(anchor, sch);
+                } else if self.schemas.get(sch).dynamic_anchor == dref.anchor {
                     // initial target has matching $dynamicAnchor
                     sch = self.resolve_dynamic_anchor(anchor, sch);
                 }
@@ -606,7 +788,9 @@ impl<'v, 's, 'd, 'e> Validator<'v, 's, 'd, 'e> {
         if let Err(err) = self._validate_self(sch, kw.into(), false) {
             let url = &self.schemas.get(sch).loc;
             let mut ref_err = self.error(ErrorKind::Reference { kw, url });
-            if let ErrorKind::Group = err.kind {
+            if ref_err.message.is_some() {
+                // message replaces errors from referenced schema
+            } else if let (ErrorKind::Group, None) = (&err.kind, &err.message) {
                 ref_err.causes = err.causes;
             } else {
                 ref_err.causes.push(err);
@@ -659,6 +843,9 @@ impl<'v, 's, 'd, 'e> Validator<'v, 's, 'd, 'e> {
         macro_rules! add_err {
             ($result:expr) => {
                 if let Err(e) = $result {
+                    if self.aborted() {
+                        return;
+                    }
                     self.errors.push(e);
                 }
             };
@@ -671,6 +858,46 @@ impl<'v, 's, 'd, 'e> Validator<'v, 's, 'd, 'e> {
             }
         }
 
+        // disallow --
+        if (!s.disallow.is_empty() || !s.disallow_schemas.is_empty())
+            && self.is_type(s.disallow, &s.disallow_schemas)
+        {
+            self.add_error(kind!(Disallow));
+        }
+
+        // extends --
+        for sch in &s.extends {
+            add_err!(self.validate_self(*sch));
+        }
+
+        // discriminator --
+        // when value selects a schema, anyOf/oneOf are not evaluated
+        let mut discriminated = false;
+        if let (Some(d), Value::Object(obj)) = (&s.discriminator, self.v) {
+            match obj.get(&d.prop) {
+                None => {
+                    discriminated = !d.mapping.is_empty();
+                    let (prop, got) = (d.prop.as_str(), None);
+                    self.add_error(ErrorKind::Discriminator { prop, got });
+                }
+                Some(pvalue) if !d.mapping.is_empty() => {
+                    discriminated = true;
+                    let sch = match pvalue {
+                        Value::String(name) => d.mapping.get(name),
+                        _ => None,
+                    };
+                    match sch {
+                        Some(sch) => add_err!(self.validate_self(*sch)),
+                        None => {
+                            let (prop, got) = (d.prop.as_str(), Some(Cow::Borrowed(pvalue)));
+                            self.add_error(ErrorKind::Discriminator { prop, got });
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
         // allOf --
         if !s.all_of.is_empty() {
             let mut errors = vec![];
@@ -688,7 +915,7 @@ impl<'v, 's, 'd, 'e> Validator<'v, 's, 'd, 'e> {
         }
 
         // anyOf --
-        if !s.any_of.is_empty() {
+        if !s.any_of.is_empty() && !discriminated {
             let mut matched = false;
             let mut errors = vec![];
             for sch in &s.any_of {
@@ -709,7 +936,7 @@ impl<'v, 's, 'd, 'e> Validator<'v, 's, 'd, 'e> {
         }
 
         // oneOf --
-        if !s.one_of.is_empty() {
+        if !s.one_of.is_empty() && !discriminated {
             let mut matched = None;
             let mut errors = vec![];
             for (i, sch) in s.one_of.iter().enumerate() {
@@ -753,6 +980,9 @@ impl<'v, 's, 'd, 'e> Validator<'v, 's, 'd, 'e> {
         macro_rules! add_err {
             ($result:expr) => {
                 if let Err(e) = $result {
+                    if self.aborted() {
+                        return;
+                    }
                     self.errors.push(e);
                 }
             };
@@ -800,6 +1030,7 @@ impl<'v, 's, 'd, 'e> Validator<'v, 's, 'd, 'e> {
         Validator {
             v,
             vloc: self.vloc,
+            budget: self.budget,
             schema,
             schemas: self.schemas,
             scope,
@@ -822,6 +1053,7 @@ impl<'v, 's, 'd, 'e> Validator<'v, 's, 'd, 'e> {
         let result = Validator {
             v: self.v,
             vloc: self.vloc,
+            budget: self.budget,
             schema,
             schemas: self.schemas,
             scope,
@@ -840,6 +1072,35 @@ impl<'v, 's, 'd, 'e> Validator<'v, 's, 'd, 'e> {
     fn validate_self(&mut self, sch: SchemaIndex) -> Result<(), ValidationError<'s, 'v>> {
         self._validate_self(sch, None, false)
     }
+
+    // tells whether validation is aborted by ValidationOptions, in which
+    // case remaining values need not be validated.
+    fn aborted(&self) -> bool {
+        self.budget.aborted.is_some()
+    }
+
+    // matches `s` against `regex`, aborting validation if backtrack
+    // limit is exceeded.
+    fn is_match(&mut self, regex: &Regex, s: &str) -> Option<bool> {
+        let matched = regex.is_match(s);
+        if matched.is_none() {
+            self.budget
+                .abort(Abort::LimitExceeded(Limit::BacktrackLimit));
+        }
+        matched
+    }
+
+    // checks whether value is one of the `types`, or valid against
+    // any of the `schemas` (draft3 union types).
+    fn is_type(&mut self, types: Types, schemas: &'s [SchemaIndex]) -> bool {
+        let v = self.v;
+        if types.contains(Type::of(v)) || (types.contains(Type::Integer) && is_integer(v)) {
+            return true;
+        }
+        schemas
+            .iter()
+            .any(|sch| self._validate_self(*sch, None, true).is_ok())
+    }
 }
 
 // error helpers
@@ -851,17 +1112,31 @@ impl<'v, 's, 'd, 'e> Validator<'v, 's, 'd, 'e> {
                 schema_url: &self.schema.loc,
                 instance_location: InstanceLocation::new(),
                 kind: ErrorKind::Group,
+                message: None,
                 causes: vec![],
             };
         }
+        let message = self
+            .keyword_message(&kind)
+            .map(|msg| custom_message(msg, &kind));
         ValidationError {
             schema_url: &self.schema.loc,
             instance_location: self.instance_location(),
             kind,
+            message,
             causes: vec![],
         }
     }
 
+    // message in `errorMessage` for keyword of `kind`
+    fn keyword_message(&self, kind: &ErrorKind) -> Option<&'s str> {
+        if self.bool_result {
+            return None;
+        }
+        let messages = &self.schema.error_message.as_ref()?.keywords;
+        messages.get(kind.schema_keyword()?).map(String::as_str)
+    }
+
     #[inline(always)]
     fn add_error(&mut self, kind: ErrorKind<'s, 'v>) {
         self.errors.push(self.error(kind));
@@ -869,15 +1144,53 @@ impl<'v, 's, 'd, 'e> Validator<'v, 's, 'd, 'e> {
 
     #[inline(always)]
     fn add_errors(&mut self, errors: Vec<ValidationError<'s, 'v>>, kind: ErrorKind<'s, 'v>) {
-        if errors.len() == 1 {
+        if errors.len() == 1 && self.keyword_message(&kind).is_none() {
             self.errors.extend(errors);
-        } else {
-            let mut err = self.error(kind);
+            return;
+        }
+        let mut err = self.error(kind);
+        if err.message.is_none() {
             err.causes = errors;
-            self.errors.push(err);
+        } // else message replaces errors from subschemas
+        self.errors.push(err);
+    }
+
+    // missing properties with message in `errorMessage` are reported separately
+    fn add_required_errors(&mut self, missing: Vec<&'s str>) {
+        let messages = self.schema.error_message.as_ref().map(|m| &m.required);
+        let Some(messages) = messages.filter(|m| !self.bool_result && !m.is_empty()) else {
+            self.add_error(kind!(Required, want: missing));
+            return;
+        };
+        let mut rest = vec![];
+        for pname in missing {
+            if let Some(msg) = messages.get(pname) {
+                let mut e = self.error(kind!(Required, want: vec![pname]));
+                e.message = Some(custom_message(msg, &e.kind));
+                self.errors.push(e);
+            } else {
+                rest.push(pname);
+            }
+        }
+        if !rest.is_empty() {
+            self.add_error(kind!(Required, want: rest));
         }
     }
 
+    // applies message in `errorMessage` for error of property `pname`
+    fn property_error(
+        &self,
+        pname: &str,
+        mut e: ValidationError<'s, 'v>,
+    ) -> ValidationError<'s, 'v> {
+        let messages = self.schema.error_message.as_ref().map(|m| &m.properties);
+        if let Some(msg) = messages.and_then(|m| m.get(pname)) {
+            e.message = Some(custom_message(msg, &e.kind));
+            e.causes.clear();
+        }
+        e
+    }
+
     fn kw_loc(&self, mut scope: &Scope) -> String {
         let mut loc = String::new();
         while let Some(parent) = scope.parent {
@@ -1077,6 +1390,15 @@ impl<'v> InstanceLocation<'v> {
     }
 }
 
+// message from `errorMessage`, with params of `kind` substituted
+fn custom_message<'s>(msg: &'s str, kind: &ErrorKind) -> Cow<'s, str> {
+    if msg.contains('{') {
+        Cow::Owned(expand(msg, &kind.params()))
+    } else {
+        Cow::Borrowed(msg)
+    }
+}
+
 impl<'v> ToString for InstanceLocation<'v> {
     fn to_string(&self) -> String {
         InstanceToken::to_string(&self.tokens)
@@ -1165,15 +1487,23 @@ impl<'s, 'v> ErrorKind<'s, 'v> {
             MinContains { got, want } => MinContains { got, want },
             MaxContains { got, want } => MaxContains { got, want },
             UniqueItems { got } => UniqueItems { got },
+            ListMapKeys { keys, got } => ListMapKeys { keys, got },
             AdditionalItems { got } => AdditionalItems { got },
             MinLength { got, want } => MinLength { got, want },
             MaxLength { got, want } => MaxLength { got, want },
             ContentEncoding { want, err } => ContentEncoding { want, err },
             ContentMediaType { got, want, err } => ContentMediaType { got, want, err },
             Not => Not,
+            Disallow => Disallow,
+            Discriminator { prop, got } => Discriminator {
+                prop,
+                got: got.map(|got| Cow::Owned(got.into_owned())),
+            },
             AllOf => AllOf,
             AnyOf => AnyOf,
             OneOf(opt) => OneOf(opt),
+            LimitExceeded { limit } => LimitExceeded { limit },
+            Cancelled => Cancelled,
         }
     }
 }
@@ -0,0 +1,46 @@
+use std::error::Error;
+
+use boon::{Catalog, Compiler, Schemas};
+use serde_json::json;
+
+#[test]
+fn test_catalog_find() -> Result<(), Box<dyn Error>> {
+    let catalog = Catalog::from_file("tests/examples/catalog/catalog.json")?;
+    assert_eq!(catalog.entries.len(), 1);
+
+    let entry = catalog
+        .find("data/person.json")
+        .ok_or("person.json not matched")?;
+    assert_eq!(entry.name, "person");
+    assert!(entry.url.starts_with("file:///"));
+    assert!(entry
+        .url
+        .ends_with("/tests/examples/catalog/person.schema.json"));
+
+    assert!(catalog.find("data\\john.person.json").is_some());
+    assert!(catalog.find("data/test.person.json").is_none());
+    assert!(catalog.find("data/address.json").is_none());
+    Ok(())
+}
+
+#[test]
+fn test_catalog_compile() -> Result<(), Box<dyn Error>> {
+    let catalog = Catalog::from_file("tests/examples/catalog/catalog.json")?;
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    let sch = catalog
+        .compile(&mut compiler, "john.person.json", &mut schemas)?
+        .ok_or("schema not found")?;
+    assert!(schemas.validate(&json!({"name": "john"}), sch).is_ok());
+    assert!(schemas.validate(&json!({"name": 1}), sch).is_err());
+
+    let sch = catalog.compile(&mut compiler, "address.json", &mut schemas)?;
+    assert!(sch.is_none());
+    Ok(())
+}
+
+#[test]
+fn test_catalog_invalid() {
+    assert!(Catalog::from_value(&json!({})).is_err());
+    assert!(Catalog::from_value(&json!({"schemas": [{"fileMatch": ["*.json"]}]})).is_err());
+}
@@ -1,7 +1,20 @@
-use std::error::Error;
+use std::{
+    env,
+    error::Error,
+    fs, process,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
 
-use boon::{Compiler, Schemas};
-use serde_json::json;
+use boon::{
+    CompileError, CompiledRegex, Compiler, DocumentCache, LoadPolicy, Position, RegexEngine,
+    RegexLimits, Schemas, SourceFormat, SourceMap, UrlContent, UrlLoader,
+};
+use serde_json::{json, Value};
 
 #[test]
 fn test_metaschema_resource() -> Result<(), Box<dyn Error>> {
@@ -85,3 +98,681 @@ fn test_compile_nonstd() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+#[test]
+fn test_load_content() -> Result<(), Box<dyn Error>> {
+    struct Remote;
+    impl UrlLoader for Remote {
+        fn load_content(&self, url: &str) -> Result<Option<UrlContent>, Box<dyn Error>> {
+            let content = match url {
+                "http://tmp.com/schema" => {
+                    UrlContent::new("{\n  // port\n  \"$ref\": \"port\",\n}")
+                        .with_extension("jsonc")
+                }
+                "http://tmp.com/port" => UrlContent::new("{\"type\": \"integer\"}")
+                    .with_media_type("application/schema+json"),
+                _ => return Err("not found".into()),
+            };
+            Ok(Some(content))
+        }
+    }
+
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    compiler.register_url_loader("http", Box::new(Remote));
+    let sch = compiler.compile("http://tmp.com/schema", &mut schemas)?;
+    assert!(schemas.validate(&json!(80), sch).is_ok());
+    assert!(schemas.validate(&json!("80"), sch).is_err());
+
+    // positions are recorded
+    assert_eq!(
+        compiler.position("http://tmp.com/schema#/$ref"),
+        Some(Position {
+            line: 3,
+            column: 11
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn test_load_with_source_map() -> Result<(), Box<dyn Error>> {
+    struct Remote(Arc<AtomicUsize>);
+    impl UrlLoader for Remote {
+        fn load(&self, _url: &str) -> Result<Value, Box<dyn Error>> {
+            Err("load_with_source_map must be used".into())
+        }
+        fn load_with_source_map(&self, _url: &str) -> Result<(Value, SourceMap), Box<dyn Error>> {
+            Ok(SourceMap::parse(
+                r#"{"type": "integer", "minimum": "0"}"#,
+                SourceFormat::Json,
+            )?)
+        }
+        fn load_content(&self, _url: &str) -> Result<Option<UrlContent>, Box<dyn Error>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(None)
+        }
+    }
+
+    for max_document_size in [None, Some(1 << 10)] {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut compiler = Compiler::new();
+        compiler.register_url_loader("http", Box::new(Remote(Arc::clone(&calls))));
+        compiler.set_load_policy(LoadPolicy {
+            max_document_size,
+            ..Default::default()
+        });
+        let result = compiler.compile("http://tmp.com/schema.json", &mut Schemas::new());
+        assert!(result.is_err(), "{max_document_size:?}");
+        assert_eq!(calls.load(Ordering::SeqCst), 1, "{max_document_size:?}");
+        assert_eq!(
+            compiler.position("http://tmp.com/schema.json#/minimum"),
+            Some(Position {
+                line: 1,
+                column: 32
+            }),
+            "{max_document_size:?}"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_load_unimplemented() {
+    struct Remote;
+    impl UrlLoader for Remote {}
+
+    let mut compiler = Compiler::new();
+    compiler.register_url_loader("http", Box::new(Remote));
+    let result = compiler.compile("http://tmp.com/schema.json", &mut Schemas::new());
+    assert!(matches!(result, Err(CompileError::LoadUrlError { .. })));
+}
+
+#[test]
+fn test_media_type() {
+    let tests = [
+        ("application/json", Some(SourceFormat::Json)),
+        (
+            "application/schema+json; charset=utf-8",
+            Some(SourceFormat::Json),
+        ),
+        ("application/yaml", Some(SourceFormat::Yaml)),
+        ("text/x-yaml", Some(SourceFormat::Yaml)),
+        ("application/toml", Some(SourceFormat::Toml)),
+        ("text/plain", None),
+    ];
+    for (media_type, want) in tests {
+        assert_eq!(
+            SourceFormat::from_media_type(media_type),
+            want,
+            "{media_type}"
+        );
+    }
+}
+
+struct CountingLoader(Arc<AtomicUsize>);
+
+impl UrlLoader for CountingLoader {
+    fn load_content(&self, url: &str) -> Result<Option<UrlContent>, Box<dyn Error>> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        let content = match url {
+            "http://tmp.com/a.json" => r#"{"$ref": "common.json"}"#,
+            "http://tmp.com/b.json" => r#"{"items": {"$ref": "common.json"}}"#,
+            "http://tmp.com/common.json" => r#"{"type": "integer"}"#,
+            _ => return Err("not found".into()),
+        };
+        Ok(Some(UrlContent::new(content)))
+    }
+}
+
+#[test]
+fn test_document_cache() -> Result<(), Box<dyn Error>> {
+    let loads = Arc::new(AtomicUsize::new(0));
+    let cache = DocumentCache::new();
+    let compile = |loc: &str| -> Result<(), CompileError> {
+        let mut compiler = Compiler::new();
+        compiler.register_url_loader("http", Box::new(CountingLoader(Arc::clone(&loads))));
+        compiler.set_document_cache(cache.clone());
+        compiler.compile(loc, &mut Schemas::new())?;
+        Ok(())
+    };
+    compile("http://tmp.com/a.json")?;
+    compile("http://tmp.com/b.json")?;
+    compile("http://tmp.com/a.json")?;
+    assert_eq!(loads.load(Ordering::SeqCst), 3);
+    assert_eq!(cache.len(), 3);
+
+    // shared across threads
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let cache = cache.clone();
+            let loads = Arc::clone(&loads);
+            thread::spawn(move || {
+                let mut compiler = Compiler::new();
+                compiler.register_url_loader("http", Box::new(CountingLoader(loads)));
+                compiler.set_document_cache(cache);
+                compiler
+                    .compile("http://tmp.com/b.json", &mut Schemas::new())
+                    .is_ok()
+            })
+        })
+        .collect();
+    for h in handles {
+        assert!(h.join().unwrap_or_default());
+    }
+    assert_eq!(loads.load(Ordering::SeqCst), 3);
+    Ok(())
+}
+
+#[test]
+fn test_document_cache_limit() -> Result<(), Box<dyn Error>> {
+    let loads = Arc::new(AtomicUsize::new(0));
+    let cache = DocumentCache::with_limit(2);
+    let compile = |loc: &str| -> Result<(), CompileError> {
+        let mut compiler = Compiler::new();
+        compiler.register_url_loader("http", Box::new(CountingLoader(Arc::clone(&loads))));
+        compiler.set_document_cache(cache.clone());
+        compiler.compile(loc, &mut Schemas::new())?;
+        Ok(())
+    };
+    compile("http://tmp.com/a.json")?; // loads a, common
+    compile("http://tmp.com/b.json")?; // loads b, evicts a
+    assert_eq!(cache.len(), 2);
+    assert_eq!(loads.load(Ordering::SeqCst), 3);
+    compile("http://tmp.com/b.json")?;
+    assert_eq!(loads.load(Ordering::SeqCst), 3);
+    compile("http://tmp.com/a.json")?;
+    assert_eq!(loads.load(Ordering::SeqCst), 4);
+
+    cache.clear();
+    assert!(cache.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_remove() -> Result<(), Box<dyn Error>> {
+    let loads = Arc::new(AtomicUsize::new(0));
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    compiler.register_url_loader("http", Box::new(CountingLoader(Arc::clone(&loads))));
+    let a = compiler.compile("http://tmp.com/a.json", &mut schemas)?;
+    let b = compiler.compile("http://tmp.com/b.json", &mut schemas)?;
+    assert_eq!(schemas.size(), 4);
+
+    // common.json is still referenced by b.json
+    assert!(schemas.remove(a));
+    assert!(!schemas.remove(a));
+    assert!(!schemas.contains(a));
+    assert!(schemas.contains(b));
+    assert_eq!(schemas.size(), 3);
+    assert!(schemas.validate(&json!([1]), b).is_ok());
+    assert!(schemas.validate(&json!(["x"]), b).is_err());
+
+    // subschema stays, while referenced by b.json
+    let items = compiler.compile("http://tmp.com/b.json#/items", &mut schemas)?;
+    assert!(schemas.remove(items));
+    assert_eq!(schemas.size(), 3);
+
+    // slot of removed schema is reused
+    compiler.unload_unused(&schemas);
+    let a2 = compiler.compile("http://tmp.com/a.json", &mut schemas)?;
+    assert_ne!(a, a2);
+    assert!(!schemas.contains(a));
+    assert!(schemas.contains(a2));
+    assert_eq!(schemas.size(), 4);
+    assert_eq!(loads.load(Ordering::SeqCst), 4);
+
+    assert!(schemas.remove(a2));
+    assert!(schemas.remove(b));
+    assert_eq!(schemas.size(), 0);
+    compiler.unload_unused(&schemas);
+    compiler.compile("http://tmp.com/b.json", &mut schemas)?;
+    assert_eq!(loads.load(Ordering::SeqCst), 6);
+    Ok(())
+}
+
+#[test]
+fn test_remove_shared() -> Result<(), Box<dyn Error>> {
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    compiler.add_resource(
+        "http://tmp.com/a.json",
+        json!({"properties": {"b": {"type": "string"}}}),
+    )?;
+    let a = compiler.compile("http://tmp.com/a.json", &mut schemas)?;
+    let b = compiler.compile("http://tmp.com/a.json#/properties/b", &mut schemas)?;
+
+    // b is still reachable from a
+    assert!(schemas.remove(b));
+    assert!(!schemas.remove(b));
+    assert!(schemas.contains(b));
+    assert!(schemas.validate(&json!("x"), b).is_ok());
+
+    assert!(schemas.remove(a));
+    assert!(!schemas.contains(a));
+    assert!(!schemas.contains(b));
+    assert_eq!(schemas.size(), 0);
+    Ok(())
+}
+
+#[test]
+fn test_unload_resource() -> Result<(), Box<dyn Error>> {
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    compiler.add_resource("http://tmp.com/a.json", json!({"type": "string"}))?;
+    let sch = compiler.compile("http://tmp.com/a.json", &mut schemas)?;
+    assert!(schemas.remove(sch));
+    compiler.unload_unused(&schemas);
+
+    // resources are loaded again
+    let sch = compiler.compile("http://tmp.com/a.json", &mut schemas)?;
+    assert!(schemas.validate(&json!("x"), sch).is_ok());
+    Ok(())
+}
+
+#[test]
+fn test_reload() -> Result<(), Box<dyn Error>> {
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    compiler.add_resource("http://tmp.com/a.json", json!({"$ref": "common.json"}))?;
+    compiler.add_resource("http://tmp.com/b.json", json!({"type": "array"}))?;
+    compiler.add_resource("http://tmp.com/common.json", json!({"type": "integer"}))?;
+    let a = compiler.compile("http://tmp.com/a.json", &mut schemas)?;
+    let b = compiler.compile("http://tmp.com/b.json", &mut schemas)?;
+
+    compiler.add_resource("http://tmp.com/common.json", json!({"type": "string"}))?;
+    compiler.invalidate("http://tmp.com/common.json")?;
+    let reloaded = compiler.reload(&mut schemas)?;
+    assert_eq!(reloaded.len(), 1);
+    let (old, new) = reloaded[0];
+    assert_eq!(old, a);
+
+    // old schema is still valid
+    assert!(schemas.validate(&json!(1), a).is_ok());
+    assert!(schemas.validate(&json!("x"), new).is_ok());
+    assert!(schemas.validate(&json!(1), new).is_err());
+    assert_eq!(
+        compiler.compile("http://tmp.com/a.json", &mut schemas)?,
+        new
+    );
+    assert!(schemas.contains(b));
+
+    schemas.remove(old);
+    assert!(!schemas.contains(a));
+    assert!(compiler.reload(&mut schemas)?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_reload_error() -> Result<(), Box<dyn Error>> {
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    compiler.add_resource("http://tmp.com/a.json", json!({"$ref": "common.json"}))?;
+    compiler.add_resource("http://tmp.com/common.json", json!({"type": "integer"}))?;
+    let a = compiler.compile("http://tmp.com/a.json", &mut schemas)?;
+
+    compiler.add_resource("http://tmp.com/common.json", json!({"type": 1}))?;
+    compiler.invalidate("http://tmp.com/common.json")?;
+    assert!(compiler.reload(&mut schemas).is_err());
+    assert_eq!(schemas.size(), 2);
+    assert_eq!(compiler.compile("http://tmp.com/a.json", &mut schemas)?, a);
+    Ok(())
+}
+
+#[test]
+fn test_invalidate_modified() -> Result<(), Box<dyn Error>> {
+    let dir = env::temp_dir().join(format!("boon-reload-{}", process::id()));
+    fs::create_dir_all(&dir)?;
+    let path = dir.join("schema.json");
+    fs::write(&path, r#"{"type": "integer"}"#)?;
+    let loc = path.to_string_lossy();
+
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    let sch = compiler.compile(&loc, &mut schemas)?;
+    assert!(compiler.invalidate_modified()?.is_empty());
+
+    fs::write(&path, r#"{"type": "string"}"#)?;
+    let mtime = SystemTime::now() + Duration::from_secs(10);
+    fs::File::options()
+        .write(true)
+        .open(&path)?
+        .set_modified(mtime)?;
+    let modified = compiler.invalidate_modified()?;
+    assert_eq!(modified.len(), 1);
+    assert!(modified[0].ends_with("/schema.json"));
+
+    let reloaded = compiler.reload(&mut schemas)?;
+    assert_eq!(reloaded.len(), 1);
+    let (old, new) = reloaded[0];
+    assert_eq!(old, sch);
+    assert!(schemas.validate(&json!("x"), new).is_ok());
+    assert!(compiler.invalidate_modified()?.is_empty());
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_invalidate_modified_cache() -> Result<(), Box<dyn Error>> {
+    let dir = env::temp_dir().join(format!("boon-reload-cache-{}", process::id()));
+    fs::create_dir_all(&dir)?;
+    let path = dir.join("schema.json");
+    fs::write(&path, r#"{"type": "integer"}"#)?;
+    let loc = path.to_string_lossy();
+
+    let cache = DocumentCache::new();
+    let mut compiler = Compiler::new();
+    compiler.set_document_cache(cache.clone());
+    compiler.compile(&loc, &mut Schemas::new())?;
+
+    fs::write(&path, r#"{"type": "string"}"#)?;
+    let mtime = SystemTime::now() + Duration::from_secs(10);
+    fs::File::options()
+        .write(true)
+        .open(&path)?
+        .set_modified(mtime)?;
+
+    // cached document is stale, so its file is modified
+    let mut compiler = Compiler::new();
+    compiler.set_document_cache(cache);
+    compiler.compile(&loc, &mut Schemas::new())?;
+    let modified = compiler.invalidate_modified()?;
+    fs::remove_dir_all(&dir)?;
+    assert_eq!(modified.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_lookaround() -> Result<(), Box<dyn Error>> {
+    let schema = json!({
+        "pattern": "^(?!tmp-)",
+        "patternProperties": {"^(\\w)\\1$": {"type": "integer"}}
+    });
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    compiler.add_resource("schema.json", schema)?;
+    let result = compiler.compile("schema.json", &mut schemas);
+    if cfg!(not(feature = "fancy-regex")) {
+        assert!(result.is_err());
+        return Ok(());
+    }
+    let sch = result?;
+    assert!(schemas.validate(&json!("name"), sch).is_ok());
+    assert!(schemas.validate(&json!("tmp-name"), sch).is_err());
+    assert!(schemas.validate(&json!({"aa": 1, "ab": "x"}), sch).is_ok());
+    assert!(schemas.validate(&json!({"aa": "x"}), sch).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_regex_limits() -> Result<(), Box<dyn Error>> {
+    let compile = |limits: RegexLimits, schema| -> Result<(), CompileError> {
+        let mut compiler = Compiler::new();
+        compiler.set_regex_limits(limits);
+        compiler.add_resource("schema.json", schema)?;
+        compiler.compile("schema.json", &mut Schemas::new())?;
+        Ok(())
+    };
+    let schema = json!({
+        "pattern": "^[a-z]{3,20}$",
+        "properties": {
+            "a": {"pattern": "^x"},
+            "b": {"patternProperties": {"^y": true}}
+        }
+    });
+    compile(RegexLimits::default(), schema.clone())?;
+
+    let tests = [
+        (
+            RegexLimits {
+                max_pattern_len: 10,
+                ..Default::default()
+            },
+            "pattern length 13 exceeds limit 10",
+        ),
+        (
+            RegexLimits {
+                max_regexes: 2,
+                ..Default::default()
+            },
+            "number of regexes exceeds limit 2",
+        ),
+        (
+            RegexLimits {
+                size_limit: 100,
+                ..Default::default()
+            },
+            "exceeds size limit",
+        ),
+    ];
+    for (limits, want) in tests {
+        let Err(e) = compile(limits, schema.clone()) else {
+            return Err(format!("{limits:?} must fail").into());
+        };
+        assert!(matches!(e, CompileError::InvalidRegex { .. }), "{e}");
+        assert!(format!("{e:#}").contains(want), "{e:#}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_regex_limits_per_document() -> Result<(), Box<dyn Error>> {
+    let mut compiler = Compiler::new();
+    compiler.set_regex_limits(RegexLimits {
+        max_regexes: 2,
+        ..Default::default()
+    });
+    compiler.add_resource(
+        "a.json",
+        json!({
+            "properties": {
+                "x": {"$id": "x.json", "pattern": "^x", "propertyNames": {"pattern": "^y"}},
+                "y": {"pattern": "^z"}
+            },
+            "$ref": "b.json"
+        }),
+    )?;
+    compiler.add_resource(
+        "b.json",
+        json!({"pattern": "^a", "items": {"pattern": "^b"}}),
+    )?;
+
+    // limit applies to each document
+    let mut schemas = Schemas::new();
+    compiler.compile("a.json#/properties/x", &mut schemas)?;
+    compiler.compile("b.json", &mut schemas)?;
+
+    // across compilations
+    let Err(e) = compiler.compile("a.json", &mut schemas) else {
+        return Err("third regex of a.json must fail".into());
+    };
+    assert!(matches!(e, CompileError::InvalidRegex { .. }), "{e}");
+    assert!(
+        format!("{e:#}").contains("number of regexes exceeds limit 2"),
+        "{e:#}"
+    );
+    Ok(())
+}
+
+struct PrefixEngine(Arc<AtomicUsize>);
+
+struct PrefixRegex(String);
+
+impl RegexEngine for PrefixEngine {
+    fn compile(
+        &self,
+        pattern: &str,
+        _limits: &RegexLimits,
+    ) -> Result<Box<dyn CompiledRegex>, Box<dyn Error>> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        match pattern.strip_prefix('^') {
+            Some(prefix) => Ok(Box::new(PrefixRegex(prefix.to_owned()))),
+            None => Err("only prefix patterns supported".into()),
+        }
+    }
+}
+
+impl CompiledRegex for PrefixRegex {
+    fn is_match(&self, s: &str) -> Option<bool> {
+        Some(s.starts_with(&self.0))
+    }
+
+    fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[test]
+fn test_regex_engine() -> Result<(), Box<dyn Error>> {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let mut compiler = Compiler::new();
+    compiler.set_regex_engine(Box::new(PrefixEngine(Arc::clone(&calls))));
+    compiler.enable_format_assertions();
+    compiler.add_resource(
+        "schema.json",
+        json!({
+            "properties": {
+                "a": {"pattern": "^x"},
+                "b": {"patternProperties": {"^y": false}},
+                "c": {"format": "regex"}
+            }
+        }),
+    )?;
+    let mut schemas = Schemas::new();
+    let sch = compiler.compile("schema.json", &mut schemas)?;
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    assert!(schemas.validate(&json!({"a": "xa"}), sch).is_ok());
+    assert!(schemas.validate(&json!({"a": "ax"}), sch).is_err());
+    assert!(schemas.validate(&json!({"b": {"ya": 1}}), sch).is_err());
+    assert!(schemas.validate(&json!({"b": {"ay": 1}}), sch).is_ok());
+    assert!(schemas.validate(&json!({"c": "^(?=a)"}), sch).is_ok());
+    assert!(schemas.validate(&json!({"c": "a"}), sch).is_err());
+    assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+    compiler.add_resource("bad.json", json!({"pattern": "x"}))?;
+    let Err(e) = compiler.compile("bad.json", &mut schemas) else {
+        return Err("pattern not supported by engine must fail".into());
+    };
+    assert!(matches!(e, CompileError::InvalidRegex { .. }), "{e}");
+    Ok(())
+}
+
+#[test]
+fn test_load_policy() -> Result<(), Box<dyn Error>> {
+    let compile = |policy: LoadPolicy, loc: &str| -> Result<(), CompileError> {
+        let mut compiler = Compiler::new();
+        let loads = Arc::new(AtomicUsize::new(0));
+        compiler.register_url_loader("http", Box::new(CountingLoader(loads)));
+        compiler.set_load_policy(policy);
+        compiler.add_resource(
+            "http://tmp.com/passwd.json",
+            json!({"$ref": "file:///etc/passwd"}),
+        )?;
+        compiler.compile(loc, &mut Schemas::new())?;
+        Ok(())
+    };
+    let http_only = LoadPolicy {
+        allowed_schemes: Some(vec!["http".to_owned()]),
+        ..Default::default()
+    };
+    compile(LoadPolicy::default(), "http://tmp.com/a.json")?;
+    compile(http_only.clone(), "http://tmp.com/a.json")?;
+
+    let tests = [
+        (
+            http_only,
+            "http://tmp.com/passwd.json",
+            "file:///etc/passwd",
+            "scheme file is not allowed",
+        ),
+        (
+            LoadPolicy {
+                allowed_prefixes: Some(vec!["http://tmp.com/a".to_owned()]),
+                ..Default::default()
+            },
+            "http://tmp.com/a.json",
+            "http://tmp.com/a.json",
+            "url does not match allowed prefixes",
+        ),
+        (
+            LoadPolicy {
+                max_documents: Some(1),
+                ..Default::default()
+            },
+            "http://tmp.com/a.json",
+            "http://tmp.com/common.json",
+            "exceeds max_documents 1",
+        ),
+        (
+            LoadPolicy {
+                max_document_size: Some(20),
+                ..Default::default()
+            },
+            "http://tmp.com/b.json",
+            "http://tmp.com/b.json",
+            "document size 34 exceeds max_document_size 20",
+        ),
+    ];
+    for (policy, loc, want_url, want_reason) in tests {
+        let Err(e) = compile(policy.clone(), loc) else {
+            return Err(format!("{policy:?} must fail for {loc}").into());
+        };
+        let CompileError::UrlNotAllowed { url, reason } = &e else {
+            return Err(format!("want UrlNotAllowed, got {e:#}").into());
+        };
+        assert_eq!(url, want_url);
+        assert_eq!(reason, want_reason);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_load_policy_failed_load() -> Result<(), Box<dyn Error>> {
+    // failed loads are not counted in max_documents
+    let mut compiler = Compiler::new();
+    let loads = Arc::new(AtomicUsize::new(0));
+    compiler.register_url_loader("http", Box::new(CountingLoader(loads)));
+    compiler.set_load_policy(LoadPolicy {
+        max_documents: Some(2),
+        ..Default::default()
+    });
+    let mut schemas = Schemas::new();
+    let result = compiler.compile("http://tmp.com/missing.json", &mut schemas);
+    assert!(matches!(result, Err(CompileError::LoadUrlError { .. })));
+    compiler.compile("http://tmp.com/a.json", &mut schemas)?;
+    Ok(())
+}
+
+#[test]
+fn test_load_policy_file_size() -> Result<(), Box<dyn Error>> {
+    let compile = |loc: &str| {
+        let mut compiler = Compiler::new();
+        compiler.set_load_policy(LoadPolicy {
+            max_document_size: Some(20),
+            ..Default::default()
+        });
+        compiler.compile(loc, &mut Schemas::new())
+    };
+    let size = fs::metadata("tests/examples/schema.json")?.len();
+    let Err(CompileError::UrlNotAllowed { reason, .. }) = compile("tests/examples/schema.json")
+    else {
+        return Err("large file must not be allowed".into());
+    };
+    assert_eq!(
+        reason,
+        format!("document size {size} exceeds max_document_size 20")
+    );
+
+    // special files are read up to limit
+    #[cfg(unix)]
+    {
+        let result = compile("/dev/zero");
+        assert!(
+            matches!(result, Err(CompileError::UrlNotAllowed { .. })),
+            "{result:?}"
+        );
+    }
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use std::fs;
 
-use boon::{CompileError, Compiler, Schemas};
+use boon::{CompileError, Compiler, DocumentCache, SandboxedFileLoader, Schemas, SourceFormat};
+use serde_json::json;
 
 fn test(path: &str) -> Result<(), CompileError> {
     let mut schemas = Schemas::new();
@@ -42,3 +43,179 @@ fn test_relative_slash_space() -> Result<(), CompileError> {
 fn test_relative_backslash_space() -> Result<(), CompileError> {
     test("tests\\examples\\sample schema.json")
 }
+
+#[test]
+fn test_map_url_prefix() -> Result<(), CompileError> {
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    compiler.map_url_prefix("https://schemas.example.com/", "tests/examples/mapped")?;
+    let sch = compiler.compile("https://schemas.example.com/person.json", &mut schemas)?;
+    let instance = serde_json::json!({"address": {}});
+    let err = schemas.validate(&instance, sch).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("https://schemas.example.com/person.json#"));
+
+    // mapped documents keep their positions
+    assert!(compiler
+        .position("https://schemas.example.com/address.json#/required")
+        .is_some());
+
+    let result = compiler.compile("https://schemas.example.com/missing.json", &mut schemas);
+    let Err(CompileError::LoadUrlError { url, .. }) = result else {
+        panic!("want load error");
+    };
+    assert!(url.ends_with("tests/examples/mapped/missing.json"), "{url}");
+    Ok(())
+}
+
+#[test]
+fn test_map_url_prefix_boundary() -> Result<(), CompileError> {
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    compiler.map_url_prefix("https://schemas.example.com/v1", "tests/examples/mapped")?;
+    compiler.compile("https://schemas.example.com/v1/address.json", &mut schemas)?;
+
+    // prefix matches only at segment boundary
+    let result = compiler.compile("https://schemas.example.com/v10/address.json", &mut schemas);
+    let Err(CompileError::UnsupportedUrlScheme { url }) = result else {
+        panic!("want unsupported scheme error");
+    };
+    assert_eq!(url, "https://schemas.example.com/v10/address.json");
+    Ok(())
+}
+
+#[test]
+fn test_sandboxed_file_loader() -> Result<(), Box<dyn std::error::Error>> {
+    let new_compiler = || -> Result<Compiler, Box<dyn std::error::Error>> {
+        let mut compiler = Compiler::new();
+        let loader = SandboxedFileLoader::new("tests/examples/mapped")?;
+        compiler.register_url_loader("file", Box::new(loader));
+        Ok(compiler)
+    };
+
+    // files under root
+    let mut schemas = Schemas::new();
+    let mut compiler = new_compiler()?;
+    compiler.map_url_prefix("https://schemas.example.com/", "tests/examples/mapped")?;
+    compiler.compile("https://schemas.example.com/person.json", &mut schemas)?;
+
+    // files outside root
+    for path in [
+        "tests/examples/schema.json",
+        "tests/examples/mapped/../schema.json",
+    ] {
+        let result = new_compiler()?.compile(path, &mut schemas);
+        let Err(e @ CompileError::LoadUrlError { .. }) = result else {
+            panic!("{path} must fail to load");
+        };
+        assert!(format!("{e:#}").contains("is outside"), "{e:#}");
+    }
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_sandboxed_file_loader_symlink() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join(format!("boon-sandbox-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let link = dir.join("schema.json");
+    _ = fs::remove_file(&link);
+    std::os::unix::fs::symlink(fs::canonicalize("tests/examples/schema.json")?, &link)?;
+
+    let mut compiler = Compiler::new();
+    compiler.register_url_loader("file", Box::new(SandboxedFileLoader::new(&dir)?));
+    let result = compiler.compile(link.to_string_lossy().as_ref(), &mut Schemas::new());
+    fs::remove_dir_all(&dir)?;
+    let Err(e) = result else {
+        panic!("symlink escaping root must fail to load");
+    };
+    assert!(format!("{e:#}").contains("is outside"), "{e:#}");
+    Ok(())
+}
+
+#[test]
+fn test_sandboxed_file_loader_cache() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join(format!("boon-sandbox-cache-{}", std::process::id()));
+    let (tenant, secret) = (dir.join("tenant"), dir.join("secret"));
+    fs::create_dir_all(&tenant)?;
+    fs::create_dir_all(&secret)?;
+    fs::write(secret.join("secret.json"), r#"{"type": "string"}"#)?;
+    fs::write(
+        tenant.join("s.json"),
+        r#"{"$ref": "../secret/secret.json"}"#,
+    )?;
+    let secret_path = secret.join("secret.json");
+    let tenant_path = tenant.join("s.json");
+
+    // unsandboxed compiler loads secret into shared cache
+    let cache = DocumentCache::new();
+    let mut compiler = Compiler::new();
+    compiler.set_document_cache(cache.clone());
+    compiler.compile(secret_path.to_string_lossy().as_ref(), &mut Schemas::new())?;
+    assert_eq!(cache.len(), 1);
+
+    let mut compiler = Compiler::new();
+    compiler.set_document_cache(cache);
+    compiler.register_url_loader("file", Box::new(SandboxedFileLoader::new(&tenant)?));
+    let result = compiler.compile(tenant_path.to_string_lossy().as_ref(), &mut Schemas::new());
+    fs::remove_dir_all(&dir)?;
+    let Err(e) = result else {
+        panic!("cached document outside root must fail to load");
+    };
+    assert!(format!("{e:#}").contains("is outside"), "{e:#}");
+    Ok(())
+}
+
+#[test]
+fn test_source_format_cache() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join(format!("boon-format-cache-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let path = dir.join("schema.json");
+    fs::write(&path, "{\"type\": \"string\", // comment\n}")?;
+    let loc = path.to_string_lossy().to_string();
+
+    let cache = DocumentCache::new();
+    let mut compiler = Compiler::new();
+    compiler.set_document_cache(cache.clone());
+    compiler.set_source_format(SourceFormat::Jsonc);
+    let jsonc = compiler.compile(&loc, &mut Schemas::new()).map(|_| ());
+
+    // parse result with overridden format must not be reused
+    let mut compiler = Compiler::new();
+    compiler.set_document_cache(cache.clone());
+    let json = compiler.compile(&loc, &mut Schemas::new()).map(|_| ());
+
+    // format applies to any loader registered for file scheme
+    let mut compiler = Compiler::new();
+    compiler.set_document_cache(cache.clone());
+    compiler.set_source_format(SourceFormat::Jsonc);
+    compiler.register_url_loader("file", Box::new(SandboxedFileLoader::new(&dir)?));
+    let sandboxed = compiler.compile(&loc, &mut Schemas::new()).map(|_| ());
+    fs::remove_dir_all(&dir)?;
+
+    jsonc?;
+    assert!(json.is_err(), "comment must not be allowed in json");
+    sandboxed?;
+    assert_eq!(cache.len(), 1);
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_question_mark() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join(format!("boon-question-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let path = dir.join("what?.json");
+    fs::write(&path, r#"{"type": "string"}"#)?;
+
+    // `?` in file path is not query
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    let result = compiler.compile(path.to_string_lossy().as_ref(), &mut schemas);
+    fs::remove_dir_all(&dir)?;
+    let sch = result?;
+    assert!(schemas.validate(&json!("x"), sch).is_ok());
+    assert!(schemas.validate(&json!(1), sch).is_err());
+    Ok(())
+}
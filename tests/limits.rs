@@ -0,0 +1,186 @@
+use std::{
+    error::Error,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use boon::{Compiler, ErrorKind, Limit, SchemaIndex, Schemas, ValidationOptions};
+use serde_json::{json, Value};
+
+fn compile(schema: Value) -> Result<(Schemas, SchemaIndex), Box<dyn Error>> {
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    compiler.add_resource("schema.json", schema)?;
+    let sch = compiler.compile("schema.json", &mut schemas)?;
+    Ok((schemas, sch))
+}
+
+fn exceeded(
+    schemas: &Schemas,
+    sch: SchemaIndex,
+    v: &Value,
+    opts: &ValidationOptions,
+) -> Option<Limit> {
+    match schemas.validate_with(v, sch, opts) {
+        Err(e) => match e.kind {
+            ErrorKind::LimitExceeded { limit } => Some(limit),
+            _ => None,
+        },
+        Ok(_) => None,
+    }
+}
+
+#[test]
+fn test_max_depth() -> Result<(), Box<dyn Error>> {
+    let (schemas, sch) = compile(json!({"items": {"$ref": "#"}}))?;
+    let opts = ValidationOptions {
+        max_depth: Some(3),
+        ..Default::default()
+    };
+    assert!(schemas.validate_with(&json!([[[1]]]), sch, &opts).is_ok());
+    assert_eq!(
+        exceeded(&schemas, sch, &json!([[[[1]]]]), &opts),
+        Some(Limit::MaxDepth)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_max_steps() -> Result<(), Box<dyn Error>> {
+    let (schemas, sch) = compile(json!({
+        "$defs": {
+            "a": {"oneOf": [{"$ref": "#/$defs/b"}, {"$ref": "#/$defs/b"}]},
+            "b": {"anyOf": [{"$ref": "#/$defs/c"}, {"$ref": "#/$defs/c"}]},
+            "c": {"items": {"type": "integer"}}
+        },
+        "items": {"$ref": "#/$defs/a"}
+    }))?;
+    let v = json!([[1, 2, 3], [4, 5, 6]]);
+    assert!(schemas.validate(&v, sch).is_err());
+
+    let opts = ValidationOptions {
+        max_steps: Some(20),
+        ..Default::default()
+    };
+    assert_eq!(exceeded(&schemas, sch, &v, &opts), Some(Limit::MaxSteps));
+    let opts = ValidationOptions {
+        max_steps: Some(1000),
+        ..Default::default()
+    };
+    assert_eq!(exceeded(&schemas, sch, &v, &opts), None);
+    Ok(())
+}
+
+#[test]
+fn test_deadline() -> Result<(), Box<dyn Error>> {
+    let (schemas, sch) = compile(json!({"type": "integer"}))?;
+    let opts = ValidationOptions {
+        deadline: Some(Instant::now()),
+        ..Default::default()
+    };
+    assert_eq!(
+        exceeded(&schemas, sch, &json!(1), &opts),
+        Some(Limit::Deadline)
+    );
+    let opts = ValidationOptions {
+        deadline: Some(Instant::now() + Duration::from_secs(60)),
+        ..Default::default()
+    };
+    assert!(schemas.validate_with(&json!(1), sch, &opts).is_ok());
+    Ok(())
+}
+
+#[test]
+fn test_limit_in_not() -> Result<(), Box<dyn Error>> {
+    // error from limit must not be ignored by `not`
+    let (schemas, sch) = compile(json!({"not": {"items": {"items": false}}}))?;
+    let opts = ValidationOptions {
+        max_depth: Some(1),
+        ..Default::default()
+    };
+    assert_eq!(
+        exceeded(&schemas, sch, &json!([[1]]), &opts),
+        Some(Limit::MaxDepth)
+    );
+    let v = json!([[1]]);
+    let e = schemas.validate_with(&v, sch, &opts).unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "at '': validation aborted, max_depth exceeded"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_cancel() -> Result<(), Box<dyn Error>> {
+    let (schemas, sch) = compile(json!({"not": {"items": {"type": "string"}}}))?;
+    let cancel = Arc::new(AtomicBool::new(false));
+    let opts = ValidationOptions {
+        cancel: Some(Arc::clone(&cancel)),
+        ..Default::default()
+    };
+    let v = json!([1, 2, 3]);
+    assert!(schemas.validate_with(&v, sch, &opts).is_ok());
+
+    // cancelled from other thread
+    let flag = Arc::clone(&cancel);
+    thread::spawn(move || flag.store(true, Ordering::Relaxed))
+        .join()
+        .unwrap();
+    let e = schemas.validate_with(&v, sch, &opts).unwrap_err();
+    assert!(matches!(e.kind, ErrorKind::Cancelled), "{e}");
+    assert_eq!(e.to_string(), "at '': validation cancelled");
+    Ok(())
+}
+
+#[test]
+fn test_abort_large_array() -> Result<(), Box<dyn Error>> {
+    // remaining items are not validated, once aborted
+    let (schemas, sch) = compile(json!({"items": {"type": "string"}, "contains": false}))?;
+    let v = Value::Array(vec![json!(0); 1_000_000]);
+    let opts = ValidationOptions {
+        max_steps: Some(10),
+        ..Default::default()
+    };
+    let e = schemas.validate_with(&v, sch, &opts).unwrap_err();
+    assert!(e.causes.is_empty());
+    assert!(matches!(
+        e.kind,
+        ErrorKind::LimitExceeded {
+            limit: Limit::MaxSteps
+        }
+    ));
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "fancy-regex")]
+fn test_backtrack_limit() -> Result<(), Box<dyn Error>> {
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    compiler.set_regex_limits(boon::RegexLimits {
+        backtrack_limit: 100,
+        ..Default::default()
+    });
+    compiler.add_resource(
+        "schema.json",
+        json!({
+            "patternProperties": {"^(?!x)(a+)+b$": false},
+            "additionalProperties": true
+        }),
+    )?;
+    let sch = compiler.compile("schema.json", &mut schemas)?;
+    assert!(schemas.validate(&json!({"ab": 1}), sch).is_err());
+
+    // exceeding limit is not treated as no match
+    let v = json!({"a".repeat(30): 1});
+    assert_eq!(
+        exceeded(&schemas, sch, &v, &ValidationOptions::default()),
+        Some(Limit::BacktrackLimit)
+    );
+    Ok(())
+}
@@ -0,0 +1,228 @@
+use std::{collections::HashMap, error::Error};
+
+use boon::{Compiler, ErrorKind, MessageCatalog, Schemas};
+use serde_json::{json, Value};
+
+struct German;
+
+impl MessageCatalog for German {
+    fn template(&self, kind: &ErrorKind) -> Option<&str> {
+        match kind {
+            ErrorKind::Schema { .. } => Some("Validierung mit {url} fehlgeschlagen"),
+            ErrorKind::Required { .. } => Some("fehlende Eigenschaften {want}"),
+            ErrorKind::Type { .. } => Some("{want} erwartet, aber {got} erhalten"),
+            ErrorKind::Pattern { .. } => Some("{got} entspricht nicht dem Muster {want}"),
+            _ => None,
+        }
+    }
+}
+
+fn schemas() -> Result<(Schemas, boon::SchemaIndex), Box<dyn Error>> {
+    let schema = json!({
+        "properties": {
+            "name": { "type": "string", "pattern": "^[a-z]+$" },
+            "age": { "type": "integer" }
+        },
+        "required": ["name", "email"]
+    });
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    compiler.add_resource("http://example.com/schema.json", schema)?;
+    let sch = compiler.compile("http://example.com/schema.json", &mut schemas)?;
+    Ok((schemas, sch))
+}
+
+#[test]
+fn test_display() -> Result<(), Box<dyn Error>> {
+    let (schemas, sch) = schemas()?;
+    let instance = json!({"name": "X", "age": "ten"});
+    let err = schemas.validate(&instance, sch).unwrap_err();
+
+    let got = err.localize(&German).to_string();
+    for want in [
+        "jsonschema Validierung mit http://example.com/schema.json# fehlgeschlagen",
+        "at '': fehlende Eigenschaften 'email'",
+        "at '/name': 'X' entspricht nicht dem Muster '^[a-z]+$'",
+        "at '/age': integer erwartet, aber string erhalten",
+    ] {
+        assert!(got.contains(want), "{want:?} not in {got}");
+    }
+
+    // default messages are unchanged
+    assert!(err.to_string().contains("missing properties 'email'"));
+    Ok(())
+}
+
+#[test]
+fn test_outputs() -> Result<(), Box<dyn Error>> {
+    let (schemas, sch) = schemas()?;
+    let instance = json!({"name": "x"});
+    let err = schemas.validate(&instance, sch).unwrap_err();
+
+    // catalog loaded from file
+    let french: HashMap<String, String> = serde_json::from_value(json!({
+        "required": "propriétés manquantes {want}"
+    }))?;
+    #[allow(deprecated)]
+    let localized: boon::LocalizedError = err.localize(&french);
+    let want = "propriétés manquantes 'email'";
+
+    let basic: Value = serde_json::to_value(localized.basic_output())?;
+    assert_eq!(basic["errors"][0]["error"], want);
+    let detailed: Value = serde_json::to_value(localized.detailed_output())?;
+    assert_eq!(detailed["errors"][0]["error"], want);
+    let hierarchical: Value = serde_json::to_value(localized.hierarchical_output())?;
+    assert_eq!(hierarchical["errors"]["required"], want);
+    let list: Value = serde_json::to_value(localized.list_output())?;
+    assert_eq!(list["details"][0]["errors"]["required"], want);
+
+    Ok(())
+}
+
+#[test]
+fn test_params() -> Result<(), Box<dyn Error>> {
+    let (schemas, sch) = schemas()?;
+    let instance = json!({"name": "x", "age": 1.5});
+    let err = schemas.validate(&instance, sch).unwrap_err();
+
+    let japanese: HashMap<String, String> = [
+        ("required", "必須プロパティ {want} がありません"),
+        ("type", "{want} が必要ですが {got} です"),
+    ]
+    .into_iter()
+    .map(|(k, v)| (k.to_owned(), v.to_owned()))
+    .collect();
+    let got = err.localize(&japanese).to_string();
+    assert!(got.contains("必須プロパティ 'email' がありません"), "{got}");
+    assert!(got.contains("integer が必要ですが number です"), "{got}");
+
+    let ErrorKind::Required { .. } = &err.causes[0].kind else {
+        panic!("want required error");
+    };
+    assert_eq!(err.causes[0].kind.id(), "required");
+    assert_eq!(
+        err.causes[0].kind.params(),
+        vec![("want", "'email'".to_owned())]
+    );
+    Ok(())
+}
+
+fn validate_with(schema: Value, instance: Value) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    compiler.add_resource("http://example.com/schema.json", schema)?;
+    let sch = compiler.compile("http://example.com/schema.json", &mut schemas)?;
+    let Err(err) = schemas.validate(&instance, sch) else {
+        return Ok(vec![]);
+    };
+    let basic: Value = serde_json::to_value(err.basic_output())?;
+    let Value::Array(units) = &basic["errors"] else {
+        return Err("errors missing in basic output".into());
+    };
+    Ok(units
+        .iter()
+        .map(|unit| format!("{}: {}", unit["instanceLocation"], unit["error"]))
+        .map(|s| s.replace('"', ""))
+        .collect())
+}
+
+#[test]
+fn test_error_message_string() -> Result<(), Box<dyn Error>> {
+    let schema = json!({
+        "properties": {
+            "age": {
+                "type": "integer",
+                "minimum": 18,
+                "errorMessage": "age must be an adult's age"
+            }
+        }
+    });
+    let got = validate_with(schema.clone(), json!({"age": 10}))?;
+    assert_eq!(got, ["/age: age must be an adult's age"]);
+    let got = validate_with(schema, json!({"age": "ten"}))?;
+    assert_eq!(got, ["/age: age must be an adult's age"]);
+    Ok(())
+}
+
+#[test]
+fn test_error_message_object() -> Result<(), Box<dyn Error>> {
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "name": { "type": "string", "minLength": 2 },
+            "zip": { "type": "string", "pattern": "^[0-9]{5}$" }
+        },
+        "required": ["name", "email", "phone"],
+        "additionalProperties": false,
+        "errorMessage": {
+            "type": "must be an object",
+            "additionalProperties": "unknown fields {got}",
+            "required": { "email": "email is required" },
+            "properties": { "zip": "zip must have 5 digits" }
+        }
+    });
+    let got = validate_with(schema.clone(), json!([]))?;
+    assert_eq!(got, [": must be an object"]);
+
+    let instance = json!({"name": "x", "zip": "abc", "extra": 1});
+    let mut got = validate_with(schema, instance)?;
+    got.sort();
+    assert_eq!(
+        got,
+        [
+            "/name: length must be >=2, but got 1",
+            "/zip: zip must have 5 digits",
+            ": email is required",
+            ": missing properties 'phone'",
+            ": unknown fields 'extra'",
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_error_message_output_keys() -> Result<(), Box<dyn Error>> {
+    let schema = json!({
+        "required": ["email", "phone"],
+        "errorMessage": { "required": { "email": "email is required" } }
+    });
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    compiler.add_resource("schema.json", schema)?;
+    let sch = compiler.compile("schema.json", &mut schemas)?;
+    let instance = json!({});
+    let err = schemas.validate(&instance, sch).unwrap_err();
+
+    // errors of same keyword share single key
+    let output = err.hierarchical_output().to_string();
+    assert_eq!(output.matches(r#""required""#).count(), 1, "{output}");
+    let output: Value = serde_json::from_str(&output)?;
+    assert_eq!(
+        output["errors"]["required"],
+        "email is required; missing properties 'phone'"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_error_message_display() -> Result<(), Box<dyn Error>> {
+    let schema = json!({
+        "allOf": [{ "minimum": 1 }, { "multipleOf": 2 }],
+        "errorMessage": { "allOf": "must be positive and even" }
+    });
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    compiler.add_resource("schema.json", schema)?;
+    let sch = compiler.compile("schema.json", &mut schemas)?;
+    let instance = json!(-1);
+    let err = schemas.validate(&instance, sch).unwrap_err();
+
+    // schema-authored message takes precedence over catalog
+    let got = err.localize(&German).to_string();
+    assert!(got.ends_with("at '': must be positive and even"), "{got}");
+    let ErrorKind::AllOf = err.causes[0].kind else {
+        panic!("want allOf error");
+    };
+    assert!(err.causes[0].causes.is_empty());
+    Ok(())
+}
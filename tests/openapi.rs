@@ -0,0 +1,242 @@
+use std::error::Error;
+
+use boon::{CompileError, Compiler, OpenApiError};
+use serde_json::{json, Value};
+
+fn petstore(openapi: &str) -> Value {
+    json!({
+        "openapi": openapi,
+        "info": { "title": "petstore", "version": "1.0" },
+        "paths": {
+            "/pets": {
+                "post": {
+                    "operationId": "addPet",
+                    "requestBody": { "$ref": "#/components/requestBodies/Pet" },
+                    "responses": {
+                        "201": {
+                            "description": "created",
+                            "content": {
+                                "application/json": { "schema": { "$ref": "#/components/schemas/Pet" } }
+                            }
+                        },
+                        "4XX": {
+                            "description": "client error",
+                            "content": {
+                                "application/*": { "schema": { "$ref": "#/components/schemas/Error" } }
+                            }
+                        },
+                        "default": {
+                            "description": "unexpected",
+                            "content": { "text/plain": {} }
+                        }
+                    }
+                },
+                "get": {
+                    "responses": { "200": { "description": "no operationId" } }
+                }
+            }
+        },
+        "components": {
+            "requestBodies": {
+                "Pet": {
+                    "content": {
+                        "application/json": { "schema": { "$ref": "#/components/schemas/Pet" } }
+                    }
+                }
+            },
+            "schemas": {
+                "Pet": {
+                    "type": "object",
+                    "properties": {
+                        "name": { "type": "string" },
+                        "tag": { "type": "string", "nullable": true }
+                    },
+                    "required": ["name"]
+                },
+                "Error": {
+                    "type": "object",
+                    "required": ["code"]
+                }
+            }
+        }
+    })
+}
+
+#[test]
+fn test_openapi31() -> Result<(), Box<dyn Error>> {
+    let mut compiler = Compiler::new();
+    compiler.add_resource("petstore.json", petstore("3.1.0"))?;
+    let api = compiler.compile_openapi("petstore.json")?;
+
+    let (pet, no_name) = (json!({"name": "x"}), json!({"tag": "x"}));
+    assert!(api
+        .validate_request("addPet", "application/json", &pet)
+        .is_ok());
+    let result = api.validate_request("addPet", "application/json", &no_name);
+    assert!(matches!(result, Err(OpenApiError::ValidationError(_))));
+
+    // nullable is not a keyword in 3.1
+    let null_tag = json!({"name": "x", "tag": null});
+    let result = api.validate_response("addPet", 201, "application/json", &null_tag);
+    assert!(result.is_err());
+
+    // media type parameters are ignored
+    let result = api.validate_response("addPet", 201, "Application/JSON; charset=utf-8", &pet);
+    assert!(result.is_ok());
+
+    // status range and media type range
+    let (error, empty) = (json!({"code": 1}), json!({}));
+    let result = api.validate_response("addPet", 404, "application/problem+json", &error);
+    assert!(result.is_ok());
+    let result = api.validate_response("addPet", 404, "application/json", &empty);
+    assert!(result.is_err());
+
+    // default response without schema
+    let text = json!("oops");
+    assert!(api
+        .validate_response("addPet", 500, "text/plain", &text)
+        .is_ok());
+
+    let result = api.validate_response("addPet", 500, "application/json", &empty);
+    assert!(matches!(
+        result,
+        Err(OpenApiError::MediaTypeNotFound { .. })
+    ));
+    let result = api.validate_request("listPets", "application/json", &empty);
+    assert!(matches!(
+        result,
+        Err(OpenApiError::OperationNotFound { .. })
+    ));
+
+    let sch = api.component("Pet").unwrap();
+    assert!(api.schemas().validate(&json!({"name": 1}), sch).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_openapi30() -> Result<(), Box<dyn Error>> {
+    let mut compiler = Compiler::new();
+    compiler.add_resource("petstore.json", petstore("3.0.3"))?;
+    let api = compiler.compile_openapi("petstore.json")?;
+
+    let null_tag = json!({"name": "x", "tag": null});
+    let result = api.validate_response("addPet", 201, "application/json", &null_tag);
+    assert!(result.is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn test_openapi_dialect() -> Result<(), Box<dyn Error>> {
+    let mut doc = petstore("3.1.0");
+    doc["jsonSchemaDialect"] = "http://json-schema.org/draft-07/schema#".into();
+    doc["components"]["schemas"]["Pet"]["dependencies"] = json!({"tag": ["name"]});
+    doc["components"]["schemas"]["Pet"]["required"] = json!([]);
+
+    let mut compiler = Compiler::new();
+    compiler.add_resource("petstore.json", doc)?;
+    let api = compiler.compile_openapi("petstore.json")?;
+
+    // dependencies is honored in draft-07
+    let tag = json!({"tag": "x"});
+    let result = api.validate_request("addPet", "application/json", &tag);
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_openapi_unsupported() -> Result<(), Box<dyn Error>> {
+    let mut compiler = Compiler::new();
+    compiler.add_resource("swagger.json", json!({"swagger": "2.0"}))?;
+    let result = compiler.compile_openapi("swagger.json");
+    assert!(matches!(result, Err(CompileError::InvalidOpenApi { .. })));
+
+    Ok(())
+}
+
+#[test]
+fn test_openapi_compile_twice() -> Result<(), Box<dyn Error>> {
+    let mut compiler = Compiler::new();
+    compiler.add_resource("petstore.json", petstore("3.1.0"))?;
+    compiler.compile_openapi("petstore.json")?;
+    let api = compiler.compile_openapi("petstore.json")?;
+    let pet = json!({"name": "tom"});
+    assert!(api
+        .validate_request("addPet", "application/json", &pet)
+        .is_ok());
+
+    // document compiled with other draft
+    let mut compiler = Compiler::new();
+    compiler.add_resource("petstore.json", petstore("3.0.3"))?;
+    compiler.compile("petstore.json", &mut boon::Schemas::new())?;
+    let result = compiler.compile_openapi("petstore.json");
+    assert!(matches!(result, Err(CompileError::InvalidOpenApi { .. })));
+    Ok(())
+}
+
+#[test]
+fn test_openapi_duplicate_operation_id() -> Result<(), Box<dyn Error>> {
+    let mut doc = petstore("3.1.0");
+    doc["paths"]["/pets"]["get"]["operationId"] = "addPet".into();
+    let mut compiler = Compiler::new();
+    compiler.add_resource("petstore.json", doc)?;
+    let Err(e) = compiler.compile_openapi("petstore.json") else {
+        return Err("duplicate operationId must fail".into());
+    };
+    assert!(matches!(e, CompileError::InvalidOpenApi { .. }), "{e}");
+    assert!(
+        e.to_string().contains("duplicate operationId 'addPet'"),
+        "{e}"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_openapi_unsupported_dialect() -> Result<(), Box<dyn Error>> {
+    let mut doc = petstore("3.1.0");
+    doc["jsonSchemaDialect"] = "https://example.com/dialect".into();
+    let mut compiler = Compiler::new();
+    compiler.add_resource("petstore.json", doc)?;
+    let result = compiler.compile_openapi("petstore.json");
+    assert!(matches!(result, Err(CompileError::InvalidOpenApi { .. })));
+
+    // document is kept for later use
+    let result = compiler.compile_openapi("petstore.json");
+    assert!(matches!(result, Err(CompileError::InvalidOpenApi { .. })));
+    Ok(())
+}
+
+#[test]
+fn test_openapi_discriminator_mapping() -> Result<(), Box<dyn Error>> {
+    let mut doc = petstore("3.0.3");
+    doc["components"]["schemas"]["Cat"] = json!({"properties": {"lives": {"type": "integer"}}});
+    doc["components"]["schemas"]["Pets"] = json!({
+        "id": "http://example.com/pets.json",
+        "oneOf": [{"$ref": "petstore.json#/components/schemas/Cat"}],
+        "discriminator": {"propertyName": "kind", "mapping": {"cat": "Cat"}}
+    });
+    let mut compiler = Compiler::new();
+    compiler.add_resource("http://example.com/petstore.json", doc.clone())?;
+    let api = compiler.compile_openapi("http://example.com/petstore.json")?;
+    let Some(sch) = api.component("Pets") else {
+        return Err("want component Pets".into());
+    };
+
+    // schema name is resolved against document, not base url of Pets
+    let cat = json!({"kind": "cat", "lives": 9});
+    assert!(api.schemas().validate(&cat, sch).is_ok());
+    let cat = json!({"kind": "cat", "lives": "9"});
+    assert!(api.schemas().validate(&cat, sch).is_err());
+
+    doc["components"]["schemas"]["Pets"]["discriminator"]["mapping"]["dog"] = "Dog".into();
+    let mut compiler = Compiler::new();
+    compiler.add_resource("http://example.com/petstore.json", doc)?;
+    let Err(e) = compiler.compile_openapi("http://example.com/petstore.json") else {
+        return Err("mapping to missing schema must fail".into());
+    };
+    assert!(matches!(e, CompileError::InvalidOpenApi { .. }), "{e}");
+    assert!(e.to_string().contains("\"Dog\""), "{e}");
+    Ok(())
+}
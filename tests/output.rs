@@ -2,7 +2,7 @@ use std::{env, error::Error, fs::File, path::Path};
 
 use boon::{Compiler, Draft, Schemas};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
 
 #[test]
 fn test_suites() -> Result<(), Box<dyn Error>> {
@@ -101,6 +101,74 @@ fn test_folder(suite: &str, folder: &str, draft: Draft) -> Result<(), Box<dyn Er
     Ok(())
 }
 
+#[test]
+fn test_next_outputs() -> Result<(), Box<dyn Error>> {
+    let schema = json!({
+        "$schema": "https://json-schema.org/draft/next/schema",
+        "$defs": { "name": { "type": "string", "minLength": 2 } },
+        "required": ["age"],
+        "properties": {
+            "name": { "$ref": "#/$defs/name" }
+        }
+    });
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    let url = "http://output-tests/schema";
+    compiler.add_resource(url, schema)?;
+    let sch = compiler.compile(url, &mut schemas)?;
+    let instance = json!({"name": "a"});
+    let Err(e) = schemas.validate(&instance, sch) else {
+        panic!("validation must fail");
+    };
+
+    let hierarchical = serde_json::to_value(e.hierarchical_output())?;
+    assert_eq!(
+        hierarchical,
+        json!({
+            "valid": false,
+            "evaluationPath": "",
+            "schemaLocation": "http://output-tests/schema#",
+            "instanceLocation": "",
+            "errors": { "required": "missing properties 'age'" },
+            "details": [{
+                "valid": false,
+                "evaluationPath": "/properties/name/$ref",
+                "schemaLocation": "http://output-tests/schema#/$defs/name",
+                "instanceLocation": "/name",
+                "errors": { "minLength": "length must be >=2, but got 1" }
+            }]
+        })
+    );
+
+    let list = serde_json::to_value(e.list_output())?;
+    assert_eq!(
+        list,
+        json!({
+            "valid": false,
+            "evaluationPath": "",
+            "schemaLocation": "http://output-tests/schema#",
+            "instanceLocation": "",
+            "details": [
+                {
+                    "valid": false,
+                    "evaluationPath": "",
+                    "schemaLocation": "http://output-tests/schema#",
+                    "instanceLocation": "",
+                    "errors": { "required": "missing properties 'age'" }
+                },
+                {
+                    "valid": false,
+                    "evaluationPath": "/properties/name/$ref",
+                    "schemaLocation": "http://output-tests/schema#/$defs/name",
+                    "instanceLocation": "/name",
+                    "errors": { "minLength": "length must be >=2, but got 1" }
+                }
+            ]
+        })
+    );
+    Ok(())
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct Group {
     description: String,
@@ -0,0 +1,241 @@
+use std::error::Error;
+
+use boon::{CompileError, Compiler, ParseError, Position, Schemas, SourceFormat, SourceMap};
+use serde_json::{json, Value};
+
+#[test]
+fn test_instance_positions() -> Result<(), Box<dyn Error>> {
+    let schema = json!({
+        "properties": {
+            "spec": {
+                "properties": {
+                    "ports": { "items": { "type": "integer" } }
+                }
+            }
+        }
+    });
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    compiler.add_resource("schema.json", schema)?;
+    let sch = compiler.compile("schema.json", &mut schemas)?;
+
+    let text = r#"{
+  "spec": {
+    "ports": [80, "http"]
+  }
+}"#;
+    let (instance, source_map) = SourceMap::parse_json(text)?;
+    let err = schemas.validate(&instance, sch).unwrap_err();
+    let report = err.with_source_map(&source_map);
+
+    let got = report.to_string();
+    assert!(
+        got.contains("at '/spec/ports/1' (3:19): want integer"),
+        "{got}"
+    );
+
+    let basic: Value = serde_json::to_value(report.basic_output())?;
+    assert_eq!(
+        basic["errors"][0]["instancePosition"],
+        json!({"line": 3, "column": 19})
+    );
+    let unit = report.detailed_output();
+    assert_eq!(
+        unit.instance_position,
+        Some(Position { line: 1, column: 1 })
+    );
+
+    // without source map
+    let basic: Value = serde_json::to_value(err.basic_output())?;
+    assert_eq!(basic["errors"][0].get("instancePosition"), None);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "yaml")]
+fn test_yaml_positions() -> Result<(), Box<dyn Error>> {
+    let schema = json!({"additionalProperties": {"type": "string"}});
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    compiler.add_resource("schema.json", schema)?;
+    let sch = compiler.compile("schema.json", &mut schemas)?;
+
+    let (instance, source_map) = SourceMap::parse_yaml("name: boon\nversion: 1\n")?;
+    let err = schemas.validate(&instance, sch).unwrap_err();
+    let got = err.with_source_map(&source_map).to_string();
+    assert!(got.contains("at '/version' (2:10)"), "{got}");
+
+    Ok(())
+}
+
+#[test]
+fn test_compile_error_positions() -> Result<(), Box<dyn Error>> {
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    let Err(e) = compiler.compile("tests/examples/duplicate-anchor.json", &mut schemas) else {
+        return Err("want compile error".into());
+    };
+    let Some(loc) = e.location() else {
+        return Err(format!("want location for {e}").into());
+    };
+    assert!(loc.ends_with("duplicate-anchor.json#/$defs/b"), "{loc}");
+    assert_eq!(
+        compiler.position(&loc),
+        Some(Position {
+            line: 4,
+            column: 10
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn test_metaschema_error_positions() -> Result<(), Box<dyn Error>> {
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    let Err(e) = compiler.compile(
+        "tests/examples/invalid-schema.json#/properties",
+        &mut schemas,
+    ) else {
+        return Err("want compile error".into());
+    };
+    let CompileError::ValidationError { url, src } = &e else {
+        return Err(format!("want validation error, but got {e}").into());
+    };
+    assert_eq!(
+        compiler.position(url),
+        Some(Position { line: 1, column: 1 })
+    );
+    let Some(source_map) = compiler.source_map(url) else {
+        return Err("want source map".into());
+    };
+    let got = src.with_source_map(&source_map).to_string();
+    assert!(
+        got.contains("at '/properties/name/minLength' (5:20)"),
+        "{got}"
+    );
+
+    // resources added as json have no positions
+    compiler.add_resource(
+        "schema.json",
+        json!({"$defs": {"a": {"$anchor": "x"}, "b": {"$anchor": "x"}}}),
+    )?;
+    let Err(e) = compiler.compile("schema.json", &mut schemas) else {
+        return Err("want compile error".into());
+    };
+    assert!(e.location().is_some());
+    assert_eq!(e.location().and_then(|loc| compiler.position(&loc)), None);
+    Ok(())
+}
+
+#[test]
+fn test_jsonc() -> Result<(), Box<dyn Error>> {
+    let text = r#"{
+  // ports to listen
+  "ports": [80, /* https */ "443",],
+}"#;
+    let (instance, source_map) = SourceMap::parse(text, SourceFormat::Jsonc)?;
+    assert_eq!(instance, json!({"ports": [80, "443"]}));
+    assert_eq!(
+        source_map.get("/ports/1"),
+        Some(Position {
+            line: 3,
+            column: 29
+        })
+    );
+
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    compiler.add_resource_text(
+        "schema.jsonc",
+        "{\"properties\": {\"ports\": {\"items\": {\"type\": \"integer\"}}}, // ports\n}",
+    )?;
+    let sch = compiler.compile("schema.jsonc", &mut schemas)?;
+    let err = schemas.validate(&instance, sch).unwrap_err();
+    let got = err.with_source_map(&source_map).to_string();
+    assert!(got.contains("at '/ports/1' (3:29)"), "{got}");
+    Ok(())
+}
+
+#[test]
+fn test_parse_error_position() -> Result<(), Box<dyn Error>> {
+    let mut compiler = Compiler::new();
+    let result = compiler.add_resource_text("schema.json", "{\n  \"type\": \"object\",,\n}");
+    let Err(CompileError::LoadUrlError { src, .. }) = result else {
+        return Err("want load error".into());
+    };
+    let Some(e) = src.downcast_ref::<ParseError>() else {
+        return Err(format!("want parse error, but got {src}").into());
+    };
+    assert_eq!(
+        e.position,
+        Some(Position {
+            line: 2,
+            column: 20
+        })
+    );
+
+    // explicit format
+    compiler.set_source_format(SourceFormat::Jsonc);
+    compiler.add_resource_text("schema.json", "{\"type\": \"object\", // comment\n}")?;
+    compiler.compile("schema.json", &mut Schemas::new())?;
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "json5")]
+fn test_json5() -> Result<(), Box<dyn Error>> {
+    let (v, _) = SourceMap::parse("{port: 0x50, name: 'web',}", SourceFormat::Json5)?;
+    assert_eq!(v, json!({"port": 80, "name": "web"}));
+    let Err(e) = SourceMap::parse("{port: }", SourceFormat::Json5) else {
+        return Err("want parse error".into());
+    };
+    assert_eq!(e.position, Some(Position { line: 1, column: 8 }));
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "toml")]
+fn test_toml() -> Result<(), Box<dyn Error>> {
+    let text = "name = 'web'\n\n[server]\nports = [80, 443]\nstarted = 1979-05-27T07:32:00Z\n";
+    let (v, _) = SourceMap::parse(text, SourceFormat::Toml)?;
+    assert_eq!(
+        v,
+        json!({"name": "web", "server": {"ports": [80, 443], "started": "1979-05-27T07:32:00Z"}})
+    );
+    let Err(e) = SourceMap::parse("name = 'web'\nport = \n", SourceFormat::Toml) else {
+        return Err("want parse error".into());
+    };
+    assert_eq!(e.position.map(|p| p.line), Some(2));
+    Ok(())
+}
+
+#[test]
+fn test_validate_schema() -> Result<(), Box<dyn Error>> {
+    let mut compiler = Compiler::new();
+    let Err(e) = compiler.validate_schema("tests/examples/invalid-schema.json")? else {
+        return Err("want metaschema validation error".into());
+    };
+    let source_map = compiler
+        .source_map("tests/examples/invalid-schema.json")
+        .ok_or("source map not found")?;
+    let msg = format!("{:#}", e.with_source_map(&source_map));
+    assert!(
+        msg.contains("at '/properties/name/minLength' (5:20)"),
+        "{msg}"
+    );
+
+    // references are not loaded
+    compiler.add_resource("tmp.json", json!({"$ref": "missing.json"}))?;
+    assert!(compiler.validate_schema("tmp.json")?.is_ok());
+    compiler.add_resource("missing.json", json!({}))?;
+    compiler.compile("tmp.json", &mut Schemas::new())?;
+
+    // load errors
+    let e = compiler
+        .validate_schema("tests/examples/missing.json")
+        .unwrap_err();
+    assert!(matches!(e, CompileError::LoadUrlError { .. }), "{e}");
+    Ok(())
+}
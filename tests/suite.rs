@@ -38,11 +38,15 @@ fn test_suite(suite: &str) -> Result<(), Box<dyn Error>> {
     if !Path::new(suite).exists() {
         Err(format!("test suite {suite} does not exist"))?;
     }
+    test_dir(suite, "draft3", Draft::V3)?;
     test_dir(suite, "draft4", Draft::V4)?;
     test_dir(suite, "draft6", Draft::V6)?;
     test_dir(suite, "draft7", Draft::V7)?;
     test_dir(suite, "draft2019-09", Draft::V2019_09)?;
     test_dir(suite, "draft2020-12", Draft::V2020_12)?;
+    test_dir(suite, "draft-next", Draft::Next)?;
+    test_dir(suite, "openapi3.0", Draft::OpenApi3_0)?;
+    test_dir(suite, "kubernetes", Draft::Kubernetes)?;
     Ok(())
 }
 
@@ -0,0 +1,99 @@
+#![cfg(feature = "yaml")]
+
+use std::{env, error::Error};
+
+use boon::{CompileError, Compiler, Schemas};
+use serde_json::json;
+use url::Url;
+
+#[test]
+fn test_ref_yaml_file() -> Result<(), Box<dyn Error>> {
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    let sch = compiler.compile("tests/examples/ref-yaml.json", &mut schemas)?;
+    let valid = json!({"person": {"firstName": "a", "lastName": "b"}});
+    assert!(schemas.validate(&valid, sch).is_ok());
+    let invalid = json!({"person": {"firstName": "a"}});
+    assert!(schemas.validate(&invalid, sch).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_multi_document_file() -> Result<(), Box<dyn Error>> {
+    // file paths have no query
+    let path = env::current_dir()?.join("tests/examples/multi.yaml");
+    let Ok(url) = Url::from_file_path(path) else {
+        return Err("want file url".into());
+    };
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    let sch = compiler.compile(&format!("{url}?document=1"), &mut schemas)?;
+    assert!(schemas.validate(&json!({"firstName": "a"}), sch).is_ok());
+    assert!(schemas.validate(&json!({"firstName": ""}), sch).is_err());
+
+    let result = compiler.compile("tests/examples/multi.yaml", &mut schemas);
+    let Err(CompileError::LoadUrlError { src, .. }) = result else {
+        return Err("want load error for multi-document file".into());
+    };
+    assert!(src.to_string().contains("found 2 documents"), "{src}");
+
+    let result = compiler.compile(&format!("{url}?document=2"), &mut schemas);
+    assert!(matches!(result, Err(CompileError::LoadUrlError { .. })));
+    Ok(())
+}
+
+#[test]
+fn test_add_resource_text() -> Result<(), Box<dyn Error>> {
+    let text = "
+type: object
+properties:
+  port:
+    $ref: '#/$defs/port'
+$defs:
+  port:
+    type: integer
+---
+type: array
+items:
+  $ref: http://example.com/schemas.yaml?document=0
+";
+    let mut schemas = Schemas::new();
+    let mut compiler = Compiler::new();
+    compiler.add_resource_text("http://example.com/schemas.yaml", text)?;
+    let sch = compiler.compile("http://example.com/schemas.yaml?document=1", &mut schemas)?;
+    assert!(schemas.validate(&json!([{"port": 80}]), sch).is_ok());
+    assert!(schemas.validate(&json!([{"port": "80"}]), sch).is_err());
+
+    // positions are recorded
+    compiler.add_resource_text(
+        "http://example.com/invalid.yaml",
+        "type: object\nminProperties: -1\n",
+    )?;
+    let Err(e) = compiler.compile("http://example.com/invalid.yaml", &mut schemas) else {
+        return Err("want compile error".into());
+    };
+    let CompileError::ValidationError { url, src } = &e else {
+        return Err(format!("want validation error, but got {e}").into());
+    };
+    let Some(source_map) = compiler.source_map(url) else {
+        return Err("want source map".into());
+    };
+    let got = src.with_source_map(&source_map).to_string();
+    assert!(got.contains("at '/minProperties' (2:16)"), "{got}");
+
+    // positions of later document are taken from same text
+    compiler.add_resource_text(
+        "http://example.com/invalid-multi.yaml",
+        "type: object\n---\ntype: object\nmaxProperties: -1\n",
+    )?;
+    let url = "http://example.com/invalid-multi.yaml?document=1";
+    let Err(CompileError::ValidationError { src, .. }) = compiler.compile(url, &mut schemas) else {
+        return Err("want validation error".into());
+    };
+    let Some(source_map) = compiler.source_map(url) else {
+        return Err("want source map".into());
+    };
+    let got = src.with_source_map(&source_map).to_string();
+    assert!(got.contains("at '/maxProperties' (4:16)"), "{got}");
+    Ok(())
+}